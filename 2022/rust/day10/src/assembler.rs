@@ -0,0 +1,140 @@
+use anyhow::{Result, anyhow};
+use crate::cpu::{Instruction, Opcode};
+use crate::crt::Crt;
+
+pub fn parse_image(lines: impl Iterator<Item = String>, crt: &Crt) -> Result<Vec<bool>> {
+    let mut image = vec![];
+    let mut rows = 0;
+    for (i, line) in lines.enumerate() {
+        if line.len() != crt.width {
+            return Err(anyhow!("Row {} has {} pixels instead of {}", i + 1, line.len(), crt.width));
+        }
+        for (j, c) in line.chars().enumerate() {
+            match c {
+                '#' => image.push(true),
+                '.' => image.push(false),
+                _ => return Err(anyhow!("Unexpected character '{c}' at row {}, column {}", i + 1, j + 1)),
+            }
+        }
+        rows += 1;
+    }
+
+    if rows != crt.height {
+        return Err(anyhow!("Image has {rows} rows instead of {}", crt.height));
+    }
+    Ok(image)
+}
+
+#[derive(Clone, Copy)]
+struct Step {
+    from_cycle: usize,
+    from_x: i32,
+    opcode: Opcode,
+}
+
+// Sprite positions far enough outside the screen are all equivalent, so the search only
+// needs X values from the first one hiding the sprite on the left to the first one on the right.
+struct Search<'a> {
+    crt: &'a Crt,
+    image: &'a [bool],
+    min_x: i32,
+    max_x: i32,
+    cost: Vec<Vec<usize>>,
+    steps: Vec<Vec<Option<Step>>>,
+}
+
+// Finds the program that draws the image with the least number of wrong pixels, so when the
+// image can't be drawn exactly the wrong pixels of the best attempt are the ones to report.
+pub fn assemble(image: &[bool], crt: &Crt) -> Result<Vec<Instruction>> {
+    let (left, right) = crt.sprite_reach();
+    let min_x = -right - 1;
+    let max_x = crt.width as i32 + left;
+    let x_count = (max_x - min_x + 1) as usize;
+    let cycles = image.len();
+    let mut search = Search {
+        crt,
+        image,
+        min_x,
+        max_x,
+        cost: vec![vec![usize::MAX; x_count]; cycles + 1],
+        steps: vec![vec![None; x_count]; cycles + 1],
+    };
+    search.run()?;
+
+    let mut x = (min_x..=max_x).min_by_key(|&x| search.cost[cycles][search.index(x)]).unwrap();
+    let mut cycle = cycles;
+    let mut program = vec![];
+    let mut unreachable = vec![];
+    while cycle > 0 {
+        let step = search.steps[cycle][search.index(x)].unwrap();
+        let operand = x - step.from_x;
+        for c in step.from_cycle..cycle {
+            if search.mismatch(c, step.from_x) > 0 {
+                unreachable.push((c / crt.width + 1, c % crt.width + 1));
+            }
+        }
+        program.push((step.opcode, operand));
+        cycle = step.from_cycle;
+        x = step.from_x;
+    }
+
+    if !unreachable.is_empty() {
+        unreachable.reverse();
+        let pixels = unreachable.iter().map(|(row, col)| format!("({row}, {col})")).collect::<Vec<_>>();
+        return Err(anyhow!("Image can't be drawn, pixels (row, column) that can't be reached: {}", pixels.join(", ")));
+    }
+
+    Ok(program.into_iter()
+        .rev()
+        .enumerate()
+        .map(|(i, (opcode, operand))| Instruction { opcode, operand, line: i + 1 })
+        .collect())
+}
+
+impl Search<'_> {
+    fn run(&mut self) -> Result<()> {
+        if !(self.min_x..=self.max_x).contains(&1) {
+            return Err(anyhow!("Screen is too narrow for the sprite to start at X = 1"));
+        }
+
+        let start = self.index(1);
+        self.cost[0][start] = 0;
+        let cycles = self.image.len();
+        for cycle in 0..cycles {
+            for x in self.min_x..=self.max_x {
+                let current = self.cost[cycle][self.index(x)];
+                if current == usize::MAX {
+                    continue;
+                }
+
+                let after_one = current + self.mismatch(cycle, x);
+                self.relax(cycle + 1, x, after_one, Step { from_cycle: cycle, from_x: x, opcode: Opcode::Noop });
+
+                if cycle + 2 > cycles {
+                    continue;
+                }
+                let after_two = after_one + self.mismatch(cycle + 1, x);
+                for next_x in (self.min_x..=self.max_x).filter(|&next_x| next_x != x) {
+                    self.relax(cycle + 2, next_x, after_two, Step { from_cycle: cycle, from_x: x, opcode: Opcode::Addx });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn relax(&mut self, cycle: usize, x: i32, value: usize, step: Step) {
+        let i = self.index(x);
+        if value < self.cost[cycle][i] {
+            self.cost[cycle][i] = value;
+            self.steps[cycle][i] = Some(step);
+        }
+    }
+
+    fn mismatch(&self, cycle: usize, x: i32) -> usize {
+        usize::from(self.crt.is_lit(cycle, x) != self.image[cycle])
+    }
+
+    fn index(&self, x: i32) -> usize {
+        (x - self.min_x) as usize
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+use anyhow::{Result, anyhow};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Opcode {
+    Noop,
+    Addx,
+}
+
+pub struct OpcodeInfo {
+    pub opcode: Opcode,
+    pub mnemonic: &'static str,
+    pub operands: usize,
+    pub cycles: usize,
+}
+
+pub const INSTRUCTION_SET: [OpcodeInfo; 2] = [
+    OpcodeInfo { opcode: Opcode::Noop, mnemonic: "noop", operands: 0, cycles: 1 },
+    OpcodeInfo { opcode: Opcode::Addx, mnemonic: "addx", operands: 1, cycles: 2 },
+];
+
+impl Opcode {
+    pub fn info(&self) -> &'static OpcodeInfo {
+        INSTRUCTION_SET.iter().find(|x| x.opcode == *self).unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub operand: i32,
+    pub line: usize,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.opcode.info().operands {
+            0 => write!(f, "{}", self.opcode.info().mnemonic),
+            _ => write!(f, "{} {}", self.opcode.info().mnemonic, self.operand),
+        }
+    }
+}
+
+pub fn parse_program(lines: impl Iterator<Item = String>) -> Result<Vec<Instruction>> {
+    let mut program = vec![];
+    for (i, line) in lines.enumerate() {
+        let line_number = i + 1;
+        let mut parts = line.split_whitespace();
+        let Some(mnemonic) = parts.next() else {
+            continue;
+        };
+
+        let info = INSTRUCTION_SET.iter()
+            .find(|x| x.mnemonic == mnemonic)
+            .ok_or_else(|| anyhow!("Line {line_number}: unknown instruction '{mnemonic}'"))?;
+
+        let operands = parts.collect::<Vec<_>>();
+        if operands.len() != info.operands {
+            return Err(anyhow!("Line {line_number}: '{mnemonic}' expects {} operand(s) but got {}", info.operands, operands.len()));
+        }
+
+        let operand = match operands.first() {
+            Some(x) => x.parse().map_err(|_| anyhow!("Line {line_number}: invalid operand '{x}'"))?,
+            None => 0,
+        };
+
+        program.push(Instruction { opcode: info.opcode, operand, line: line_number });
+    }
+
+    Ok(program)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CycleState {
+    pub cycle: usize,
+    pub x: i32,
+    pub instruction: Instruction,
+}
+
+impl fmt::Display for CycleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cycle {:>4}  X = {:>3}  line {:>4}: {}", self.cycle, self.x, self.instruction.line, self.instruction)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Breakpoint {
+    Cycle(usize),
+    XEquals(i32),
+}
+
+impl Breakpoint {
+    // A value breakpoint only hits on the cycle X changes to the value, not while X keeps it.
+    fn is_hit(&self, state: &CycleState, previous_x: Option<i32>) -> bool {
+        match self {
+            Breakpoint::Cycle(cycle) => state.cycle == *cycle,
+            Breakpoint::XEquals(x) => state.x == *x && previous_x != Some(*x),
+        }
+    }
+}
+
+pub struct Cpu<'a> {
+    program: &'a [Instruction],
+    pc: usize,
+    x: i32,
+    previous_x: Option<i32>,
+    cycle: usize,
+    cycles_in_instruction: usize,
+}
+
+impl<'a> Cpu<'a> {
+    pub fn new(program: &'a [Instruction]) -> Self {
+        Cpu { program, pc: 0, x: 1, previous_x: None, cycle: 0, cycles_in_instruction: 0 }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn current_cycle(&self) -> usize {
+        self.cycle
+    }
+
+    // Runs a single cycle and returns the state during it, X is updated after the cycle ends.
+    // When X would overflow the cycle isn't run, so the CPU stays where it was.
+    pub fn step(&mut self) -> Result<Option<CycleState>> {
+        let Some(&instruction) = self.program.get(self.pc) else {
+            return Ok(None);
+        };
+        let state = CycleState { cycle: self.cycle + 1, x: self.x, instruction };
+
+        if self.cycles_in_instruction + 1 == instruction.opcode.info().cycles {
+            self.x = match instruction.opcode {
+                Opcode::Noop => self.x,
+                Opcode::Addx => self.x.checked_add(instruction.operand)
+                    .ok_or_else(|| anyhow!("Line {}: X overflows adding {} to {}", instruction.line, instruction.operand, self.x))?,
+            };
+            self.cycles_in_instruction = 0;
+            self.pc += 1;
+        } else {
+            self.cycles_in_instruction += 1;
+        }
+        self.cycle += 1;
+        self.previous_x = Some(state.x);
+
+        Ok(Some(state))
+    }
+
+    pub fn run_until(&mut self, breakpoints: &[Breakpoint]) -> Result<Option<CycleState>> {
+        loop {
+            let previous_x = self.previous_x;
+            let Some(state) = self.step()? else { break };
+            if breakpoints.iter().any(|x| x.is_hit(&state, previous_x)) {
+                return Ok(Some(state));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for Cpu<'_> {
+    type Item = Result<CycleState>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.step().transpose()
+    }
+}
@@ -0,0 +1,99 @@
+use std::str::FromStr;
+use anyhow::{Result, anyhow};
+use crate::cpu::Cpu;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Crt {
+    pub width: usize,
+    pub height: usize,
+    pub sprite_width: usize,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Crt { width: 40, height: 6, sprite_width: 3 }
+    }
+}
+
+impl Crt {
+    pub fn new(width: usize, height: usize, sprite_width: usize) -> Result<Self> {
+        if width == 0 || height == 0 || sprite_width == 0 {
+            return Err(anyhow!("Screen and sprite sizes must be positive"));
+        }
+        Ok(Crt { width, height, sprite_width })
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.width * self.height
+    }
+
+    // Pixels covered by a sprite centered at x, wider half goes to the right for even widths.
+    pub fn sprite_reach(&self) -> (i32, i32) {
+        let left = (self.sprite_width as i32 - 1) / 2;
+        let right = self.sprite_width as i32 / 2;
+        (left, right)
+    }
+
+    pub fn is_lit(&self, cycle: usize, x: i32) -> bool {
+        let column = (cycle % self.width) as i32;
+        let (left, right) = self.sprite_reach();
+        column >= x - left && column <= x + right
+    }
+
+    pub fn draw(&self, cpu: Cpu) -> Result<String> {
+        let mut drawing = String::with_capacity(self.frame_size());
+        for (i, state) in cpu.enumerate() {
+            let to_draw = if self.is_lit(i, state?.x) { '#' } else { '.' };
+            drawing.push(to_draw);
+        }
+        Ok(drawing)
+    }
+
+    // Splits a drawing into full frames, the last unfinished frame is padded with dark pixels.
+    pub fn frames(&self, drawing: &str) -> Vec<String> {
+        drawing.as_bytes()
+            .chunks(self.frame_size())
+            .map(|x| {
+                let mut frame = String::from_utf8_lossy(x).into_owned();
+                frame.push_str(&".".repeat(self.frame_size() - x.len()));
+                frame
+            })
+            .collect()
+    }
+}
+
+pub struct SampleSchedule(Vec<usize>);
+
+impl Default for SampleSchedule {
+    fn default() -> Self {
+        SampleSchedule((20..=220).step_by(40).collect())
+    }
+}
+
+impl SampleSchedule {
+    pub fn contains(&self, cycle: usize) -> bool {
+        self.0.binary_search(&cycle).is_ok()
+    }
+}
+
+// Accepts either an explicit list "20,60,100" or a range "start:step:end".
+impl FromStr for SampleSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cycles = match s.split(':').collect::<Vec<_>>().as_slice() {
+            [start, step, end] => {
+                let step: usize = step.parse()?;
+                if step == 0 {
+                    return Err(anyhow!("Sample step must be positive"));
+                }
+                (start.parse::<usize>()?..=end.parse()?).step_by(step).collect()
+            },
+            [list] => list.split(',').map(|x| x.trim().parse()).collect::<Result<Vec<usize>, _>>()?,
+            _ => return Err(anyhow!("Failed to parse sample schedule from {s}")),
+        };
+        cycles.sort_unstable();
+        cycles.dedup();
+        Ok(SampleSchedule(cycles))
+    }
+}
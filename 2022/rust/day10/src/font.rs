@@ -0,0 +1,84 @@
+use anyhow::{Result, anyhow};
+
+pub const GLYPH_WIDTH: usize = 4;
+pub const GLYPH_HEIGHT: usize = 6;
+pub const GLYPH_SPACING: usize = 1;
+
+pub const GLYPHS: [(char, [&str; GLYPH_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+    (' ', ["....", "....", "....", "....", "....", "...."]),
+];
+
+pub fn render_text(text: &str, width: usize) -> Result<Vec<String>> {
+    let mut rows = vec![String::with_capacity(width); GLYPH_HEIGHT];
+    for (i, c) in text.chars().enumerate() {
+        let glyph = GLYPHS.iter()
+            .find(|(x, _)| *x == c.to_ascii_uppercase())
+            .ok_or_else(|| anyhow!("Character '{c}' at position {} is not in the font", i + 1))?;
+
+        // Spacing only goes between letters, so the last one may end at the right edge.
+        for (row, glyph_row) in rows.iter_mut().zip(glyph.1) {
+            if i > 0 {
+                row.push_str(&".".repeat(GLYPH_SPACING));
+            }
+            row.push_str(glyph_row);
+        }
+    }
+
+    for row in rows.iter_mut() {
+        if row.len() > width {
+            return Err(anyhow!("Text '{text}' is {} pixels wide and doesn't fit into {width} columns", row.len()));
+        }
+        row.push_str(&".".repeat(width - row.len()));
+    }
+
+    Ok(rows)
+}
+
+pub fn recognize(drawing: &str, width: usize) -> Result<String> {
+    let rows = drawing.as_bytes().chunks(width).collect::<Vec<_>>();
+    if rows.len() != GLYPH_HEIGHT || rows.iter().any(|x| x.len() != width) {
+        return Err(anyhow!("Drawing must be {width} columns by {GLYPH_HEIGHT} rows"));
+    }
+    if width < GLYPH_WIDTH {
+        return Err(anyhow!("Drawing of {width} columns is narrower than a letter"));
+    }
+
+    let mut text = String::new();
+    let mut unknown = vec![];
+    let stride = GLYPH_WIDTH + GLYPH_SPACING;
+    for (i, column) in (0..=width - GLYPH_WIDTH).step_by(stride).enumerate() {
+        let cell = rows.iter().map(|row| &row[column..column + GLYPH_WIDTH]).collect::<Vec<_>>();
+        let glyph = GLYPHS.iter()
+            .find(|(_, glyph)| glyph.iter().zip(cell.iter()).all(|(a, b)| a.as_bytes() == *b));
+
+        match glyph {
+            Some((c, _)) => text.push(*c),
+            None => {
+                text.push('?');
+                unknown.push(format!("letter {} (columns {}-{})", i + 1, column + 1, column + GLYPH_WIDTH));
+            },
+        }
+    }
+
+    if !unknown.is_empty() {
+        return Err(anyhow!("Unknown glyphs in '{text}' at {}", unknown.join(", ")));
+    }
+    Ok(text.trim_end().to_owned())
+}
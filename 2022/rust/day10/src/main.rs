@@ -1,78 +1,179 @@
-use std::io::{self, BufRead};
+mod assembler;
+mod cpu;
+mod crt;
+mod font;
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use anyhow::{Result, anyhow};
+use cpu::{Breakpoint, Cpu, Instruction};
+use crt::{Crt, SampleSchedule};
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(|x| x.as_str()) {
+        Some("debug") => debug(&args[1..]),
+        Some("assemble") => assemble(&args[1..]),
+        command => read_program().and_then(|program| match command {
+            Some("part_one") => part_one(Cpu::new(&program), &parse_samples(&args)?),
+            Some("trace") => trace(Cpu::new(&program)),
+            _ => part_two(Cpu::new(&program), &parse_crt(&args)?),
+        }),
+    };
+
+    if let Err(err) = result {
+        println!("Error: {err:?}");
+    }
+}
+
+fn read_program() -> Result<Vec<Instruction>> {
     let stdin = io::stdin();
-    let lines = stdin.lock().lines();
+    let lines = stdin.lock().lines().collect::<Result<Vec<_>, _>>()?;
+    cpu::parse_program(lines.into_iter())
+}
 
-    let program_routine = ProgramRoutine::from_commands(lines.map(|x|x.unwrap()));
-    part_two(program_routine);
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|x| x == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|x| x.as_str())
 }
 
-fn part_one(program_routine: ProgramRoutine<impl Iterator<Item = String>>) {
+fn parse_crt(args: &[String]) -> Result<Crt> {
+    let default = Crt::default();
+    let parse_or = |name, default| option_value(args, name).map(|x| x.parse()).transpose().map(|x| x.unwrap_or(default));
+    Crt::new(
+        parse_or("--width", default.width)?,
+        parse_or("--height", default.height)?,
+        parse_or("--sprite", default.sprite_width)?)
+}
+
+fn parse_samples(args: &[String]) -> Result<SampleSchedule> {
+    option_value(args, "--samples")
+        .map(|x| x.parse())
+        .unwrap_or_else(|| Ok(SampleSchedule::default()))
+}
+
+fn part_one(cpu: Cpu, samples: &SampleSchedule) -> Result<()> {
     let mut sum = 0;
-    for (i, value) in program_routine.enumerate() {
-        let i = i as i32 + 1;
-        if i == 20 || i == 60 || i == 100 || i == 140 || i == 180 || i == 220 {
-            sum += i * value;
+    for state in cpu {
+        let state = state?;
+        if samples.contains(state.cycle) {
+            sum += state.cycle as i64 * state.x as i64;
         }
     }
 
     println!("Signal strength sum is: {sum}");
+    Ok(())
 }
 
-fn part_two(program_routine: ProgramRoutine<impl Iterator<Item = String>>) {
-    let mut drawing = String::with_capacity(240);
-    for (i, value) in program_routine.enumerate() {
-        let i = i as i32 % 40;
-        let to_draw = if i < value - 1 || i > value + 1 { '.' } else { '#' };
-        drawing.push(to_draw);
-    }
+fn part_two(cpu: Cpu, crt: &Crt) -> Result<()> {
+    let drawing = crt.draw(cpu)?;
+    let frames = crt.frames(&drawing);
+
+    for (i, frame) in frames.iter().enumerate() {
+        match frames.len() {
+            1 => println!("CRT drawing:"),
+            _ => println!("CRT drawing, frame {}:", i + 1),
+        }
+        for row in frame.as_bytes().chunks(crt.width) {
+            println!("{}", String::from_utf8_lossy(row));
+        }
 
-    println!("CRT drawing:");
-    for i in 0..6 {
-        println!("{}", &drawing[i*40..i*40+40]);
+        if crt.height == font::GLYPH_HEIGHT && crt.width >= font::GLYPH_WIDTH {
+            match font::recognize(frame, crt.width) {
+                Ok(text) => println!("Letters: {text}"),
+                Err(err) => println!("Can't read letters: {err}"),
+            }
+        }
     }
+    Ok(())
 }
 
-struct ProgramRoutine<T> {
-    x_value: i32,
-    next_x_value: i32,
-    skip_next: bool,
-    inner: T,
+fn assemble(args: &[String]) -> Result<()> {
+    let crt = parse_crt(args)?;
+    let rows = match option_value(args, "--text") {
+        Some(text) => font::render_text(text, crt.width)?,
+        None => io::stdin().lock().lines().collect::<Result<Vec<_>, _>>()?,
+    };
+    let image = assembler::parse_image(rows.into_iter(), &crt)?;
+    let program = assembler::assemble(&image, &crt)?;
+
+    let expected = image.iter().map(|&x| if x { '#' } else { '.' }).collect::<String>();
+    if crt.draw(Cpu::new(&program))? != expected {
+        return Err(anyhow!("Assembled program doesn't reproduce the image"));
+    }
+
+    for instruction in program {
+        println!("{instruction}");
+    }
+    Ok(())
 }
 
-impl<T> ProgramRoutine<T> 
-where T: Iterator<Item = String> {
-    fn from_commands(commands: T) -> Self {
-        ProgramRoutine {
-            x_value: 1,
-            next_x_value: 1,
-            skip_next: false,
-            inner: commands,
-        }
+fn trace(cpu: Cpu) -> Result<()> {
+    for state in cpu {
+        println!("{}", state?);
     }
+    Ok(())
 }
 
-impl<T> Iterator for ProgramRoutine<T>
-where T: Iterator<Item = String> {
-    type Item = i32;
+fn debug(args: &[String]) -> Result<()> {
+    let path = args.first().ok_or_else(|| anyhow!("Usage: day10 debug <program file>"))?;
+    let source = fs::read_to_string(path)?;
+    let program = cpu::parse_program(source.lines().map(|x| x.to_owned()))?;
+    let mut cpu = Cpu::new(&program);
+    let mut breakpoints: Vec<Breakpoint> = vec![];
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.skip_next {
-            self.skip_next = false;
-            return Some(self.x_value);
-        }
+    println!("Loaded {} instructions. Commands: step [n], continue, break cycle <n>, break x <value>, clear, print, quit", program.len());
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(cycle {}) > ", cpu.current_cycle());
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let line = line?;
+        let parts = line.split_whitespace().collect::<Vec<_>>();
 
-        self.x_value = self.next_x_value;
-        let command = self.inner.next()?;
-        if command.starts_with("addx") {
-            let (_, val) = command.split_at(5);
-            self.next_x_value = self.x_value + val.parse::<i32>().unwrap();
-            self.skip_next = true;
+        match parts.as_slice() {
+            ["step"] | ["s"] => print_state(cpu.step()),
+            ["step", n] | ["s", n] => match n.parse::<usize>() {
+                Ok(n) => {
+                    for _ in 0..n {
+                        let state = cpu.step();
+                        let failed = state.is_err();
+                        print_state(state);
+                        if failed {
+                            break;
+                        }
+                    }
+                },
+                Err(err) => println!("Invalid number of steps '{n}': {err}"),
+            },
+            ["continue"] | ["c"] => print_state(cpu.run_until(&breakpoints)),
+            ["break", "cycle", n] => match n.parse() {
+                Ok(n) => breakpoints.push(Breakpoint::Cycle(n)),
+                Err(err) => println!("Invalid cycle '{n}': {err}"),
+            },
+            ["break", "x", value] => match value.parse() {
+                Ok(value) => breakpoints.push(Breakpoint::XEquals(value)),
+                Err(err) => println!("Invalid value of X '{value}': {err}"),
+            },
+            ["clear"] => breakpoints.clear(),
+            ["print"] | ["p"] => println!("cycle {} X = {} breakpoints: {breakpoints:?}", cpu.current_cycle(), cpu.x()),
+            ["quit"] | ["q"] => return Ok(()),
+            [] => {},
+            _ => println!("Unknown command: {line}"),
         }
+    }
 
-        Some(self.x_value)
+    fn print_state(state: Result<Option<cpu::CycleState>>) {
+        match state {
+            Ok(Some(state)) => println!("{state}"),
+            Ok(None) => println!("Program finished"),
+            Err(err) => println!("Error: {err}"),
+        }
     }
 }
-
-
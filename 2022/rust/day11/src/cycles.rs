@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use anyhow::{Result, anyhow};
+
+use crate::Scene;
+
+// Items never influence each other in no relief mode, so every item can be simulated on its own.
+// Within one round an item visits each monkey at most once (it keeps moving only while thrown to
+// a monkey with a greater index), so the monkeys it visited in a round fit into a bit mask.
+struct ItemTrajectory {
+    visited_per_round: Vec<u64>,
+    cycle_start: usize,
+}
+
+impl ItemTrajectory {
+    fn trace(scene: &Scene, mut monkey: usize, mut worry_level: u64) -> Result<Self> {
+        let mut seen_at_round = HashMap::new();
+        let mut visited_per_round = vec![];
+
+        while let Entry::Vacant(entry) = seen_at_round.entry((worry_level, monkey)) {
+            entry.insert(visited_per_round.len());
+
+            let mut visited = 0;
+            loop {
+                let current = &scene.monkeys[monkey];
+                visited |= 1 << monkey;
+                worry_level = (current.operation)(&worry_level)
+                    .ok_or_else(|| anyhow!("Worry level {worry_level} overflows at monkey {monkey}"))?
+                    % scene.overall_modulo;
+                let throw_to_monkey = if worry_level.is_multiple_of(current.test_divisibility_to) { current.throw_to_if_divisible }
+                                      else { current.throw_to_if_not_divisible };
+
+                let stays_in_round = throw_to_monkey > monkey;
+                monkey = throw_to_monkey;
+                if !stays_in_round {
+                    break;
+                }
+            }
+            visited_per_round.push(visited);
+        }
+
+        let cycle_start = seen_at_round[&(worry_level, monkey)];
+        Ok(ItemTrajectory { visited_per_round, cycle_start })
+    }
+
+    fn add_inspections(&self, rounds: u64, inspections: &mut [u64]) {
+        let prefix = &self.visited_per_round[..self.cycle_start];
+        let cycle = &self.visited_per_round[self.cycle_start..];
+
+        let prefix_rounds = rounds.min(prefix.len() as u64) as usize;
+        add_visits(&prefix[..prefix_rounds], 1, inspections);
+
+        let rounds_in_cycle = rounds - prefix_rounds as u64;
+        let full_cycles = rounds_in_cycle / cycle.len() as u64;
+        let remainder = (rounds_in_cycle % cycle.len() as u64) as usize;
+        add_visits(cycle, full_cycles, inspections);
+        add_visits(&cycle[..remainder], 1, inspections);
+
+        fn add_visits(visits: &[u64], times: u64, inspections: &mut [u64]) {
+            if times == 0 {
+                return;
+            }
+            for visited in visits {
+                for (i, inspected) in inspections.iter_mut().enumerate() {
+                    if visited & (1 << i) != 0 {
+                        *inspected += times;
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn inspections_after(scene: &Scene, rounds: u64) -> Result<Vec<u64>> {
+    if scene.monkeys.len() > 64 {
+        return Err(anyhow!("Cycle detection supports at most 64 monkeys"));
+    }
+
+    let mut inspections = vec![0; scene.monkeys.len()];
+    for (i, monkey) in scene.monkeys.iter().enumerate() {
+        for item in monkey.items.iter() {
+            ItemTrajectory::trace(scene, i, item.0 % scene.overall_modulo)?
+                .add_inspections(rounds, &mut inspections);
+        }
+    }
+
+    Ok(inspections)
+}
@@ -1,81 +1,232 @@
+mod cycles;
+mod trace;
+mod worry;
+
+use std::env;
 use std::fmt;
+use std::str::FromStr;
 use std::io::{self, Read};
-use anyhow::{Result, Ok};
+use anyhow::{Result, Ok, anyhow};
 use regex::Regex;
 use lazy_static::lazy_static;
 use std::collections::VecDeque;
+use num_bigint::BigUint;
+use worry::WorryLevel;
 
 fn main() {
     let mut input = String::new();
     let stdin = io::stdin();
     stdin.lock().read_to_string(&mut input).unwrap();
 
-    let scene = Scene::parse(&input).unwrap();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match option_value(&args, "--worry").unwrap_or("u64") {
+        "u64" => run::<u64>(&input, &args),
+        "u128" => run::<u128>(&input, &args),
+        "big" => run::<BigUint>(&input, &args),
+        other => Err(anyhow!("Unknown worry level type {other}, expected u64, u128 or big")),
+    };
 
-    part_one(scene)
+    if let Err(err) = result {
+        println!("Error: {err:?}");
+    }
 }
 
-fn part_one(mut scene: Scene) {
-    for _ in 0..20 {
-        scene.run_round_with_releif();
+fn run<W: WorryLevel>(input: &str, args: &[String]) -> Result<()> {
+    match args.first().map(|x| x.as_str()) {
+        Some("part_two") => simulate(Scene::<W>::parse(input)?, Simulation::part_two().with_args(args)?),
+        Some("trace") => run_trace::<W>(input, &args[1..]),
+        Some("fast_forward") => run_fast_forward(input, &args[1..]),
+        _ => simulate(Scene::<W>::parse(input)?, Simulation::part_one().with_args(args)?),
     }
+}
 
-    let most_active_monkeys = scene.get_most_active_monkeys();
-    let level_of_monkey_business = most_active_monkeys.into_iter()
-        .take(2)
-        .map(|x| x.inspected_items_count)
-        .fold(1, |a,b| a * b);
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|x| x == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|x| x.as_str())
+}
 
-    println!("The level of monkey business is: {level_of_monkey_business}");
+fn run_trace<W: WorryLevel>(input: &str, args: &[String]) -> Result<()> {
+    let path = args.first().ok_or_else(|| anyhow!("Usage: day11 trace <file.csv|file.jsonl> [--throws] [--relief <div<k>|lcm|none>] [--rounds <n>]"))?;
+    let with_throws = args.iter().any(|x| x == "--throws");
+
+    let simulations = if option_value(args, "--relief").is_some() {
+        vec![Simulation::part_two().with_args(args)?]
+    } else {
+        vec![Simulation::part_one().with_args(args)?, Simulation::part_two().with_args(args)?]
+    };
+
+    let mut writer = trace::TraceWriter::create(path, with_throws)?;
+    for simulation in simulations {
+        writer.run(&mut Scene::<W>::parse(input)?, simulation.relief, simulation.rounds)?;
+    }
+
+    println!("Trace written to {path}");
+    Ok(())
 }
 
-fn part_two(mut scene: Scene) {
-    for _ in 0..10000 {
-        scene.run_round_no_releif();
+fn run_fast_forward(input: &str, args: &[String]) -> Result<()> {
+    let rounds: u64 = args.first()
+        .ok_or_else(|| anyhow!("Usage: day11 fast_forward <rounds> [--verify] [--top <k>]"))?
+        .parse()?;
+    let top = option_value(args, "--top").map(|x| x.parse()).transpose()?.unwrap_or(2);
+
+    let scene = Scene::<u64>::parse(input)?;
+    if args.iter().any(|x| x == "--verify") {
+        verify_fast_forward(input, 10000)?;
+    }
+
+    let inspections = cycles::inspections_after(&scene, rounds)?;
+    let level_of_monkey_business = level_of_monkey_business(inspections, top)?;
+
+    println!("The level of monkey business after {rounds} rounds is: {level_of_monkey_business}");
+    Ok(())
+}
+
+fn verify_fast_forward(input: &str, rounds: u64) -> Result<()> {
+    let mut scene = Scene::<u64>::parse(input)?;
+    let expected = cycles::inspections_after(&scene, rounds)?;
+    for _ in 0..rounds {
+        scene.run_round(Relief::ModuloLcm)?;
     }
 
-    let most_active_monkeys = scene.get_most_active_monkeys();
-    let level_of_monkey_business = most_active_monkeys.into_iter()
-        .take(2)
-        .map(|x| x.inspected_items_count)
-        .fold(1, |a,b| a as u64 * b as u64);
+    let actual = scene.monkeys.iter().map(|x| x.inspected_items_count).collect::<Vec<_>>();
+    if expected != actual {
+        return Err(anyhow!("Fast forward gives {expected:?} but simulation gives {actual:?} after {rounds} rounds"));
+    }
+
+    println!("Fast forward matches simulation after {rounds} rounds");
+    Ok(())
+}
+
+fn simulate<W: WorryLevel>(mut scene: Scene<W>, simulation: Simulation) -> Result<()> {
+    for _ in 0..simulation.rounds {
+        scene.run_round(simulation.relief)?;
+    }
+
+    let inspections = scene.monkeys.iter().map(|x| x.inspected_items_count).collect();
+    let level_of_monkey_business = level_of_monkey_business(inspections, simulation.top)?;
 
     println!("The level of monkey business is: {level_of_monkey_business}");
+    Ok(())
+}
+
+fn level_of_monkey_business(mut inspections: Vec<u64>, top: usize) -> Result<u128> {
+    inspections.sort_unstable_by_key(|x| std::cmp::Reverse(*x));
+    inspections.into_iter()
+        .take(top)
+        .try_fold(1u128, |a, b| a.checked_mul(u128::from(b)))
+        .ok_or_else(|| anyhow!("Level of monkey business overflows"))
 }
 
 #[derive(Clone, Copy)]
-struct ItemWorryLevel(u64);
+enum Relief {
+    DivideBy(u64),
+    ModuloLcm,
+    None,
+}
+
+impl FromStr for Relief {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lcm" => Ok(Relief::ModuloLcm),
+            "none" => Ok(Relief::None),
+            s if s.starts_with("div") => match s[3..].parse()? {
+                0 => Err(anyhow!("Relief can't divide by zero")),
+                k => Ok(Relief::DivideBy(k)),
+            },
+            _ => Err(anyhow!("Failed to parse Relief from {s}, expected div<k>, lcm or none")),
+        }
+    }
+}
+
+impl fmt::Display for Relief {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Relief::DivideBy(k) => write!(f, "div{k}"),
+            Relief::ModuloLcm => write!(f, "lcm"),
+            Relief::None => write!(f, "none"),
+        }
+    }
+}
 
-struct Monkey {
-    items: VecDeque<ItemWorryLevel>,
-    operation: Box<dyn Fn(u64) -> u64>,
+struct Simulation {
+    relief: Relief,
+    rounds: usize,
+    top: usize,
+}
+
+impl Simulation {
+    fn part_one() -> Self {
+        Simulation { relief: Relief::DivideBy(3), rounds: 20, top: 2 }
+    }
+
+    fn part_two() -> Self {
+        Simulation { relief: Relief::ModuloLcm, rounds: 10000, top: 2 }
+    }
+
+    fn with_args(mut self, args: &[String]) -> Result<Self> {
+        if let Some(relief) = option_value(args, "--relief") {
+            self.relief = relief.parse()?;
+        }
+        if let Some(rounds) = option_value(args, "--rounds") {
+            self.rounds = rounds.parse()?;
+        }
+        if let Some(top) = option_value(args, "--top") {
+            self.top = top.parse()?;
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Clone)]
+struct ItemWorryLevel<W>(W);
+
+#[derive(Clone)]
+struct Throw<W> {
+    from_monkey: usize,
+    to_monkey: usize,
+    worry_level: W,
+}
+
+type Operation<W> = Box<dyn Fn(&W) -> Option<W>>;
+
+struct Monkey<W> {
+    items: VecDeque<ItemWorryLevel<W>>,
+    operation: Operation<W>,
     test_divisibility_to: u64,
     throw_to_if_divisible: usize,
     throw_to_if_not_divisible: usize,
-    inspected_items_count: u32,
+    inspected_items_count: u64,
 }
 
-struct Scene {
-    monkeys: Vec<Monkey>,
+struct Scene<W = u64> {
+    monkeys: Vec<Monkey<W>>,
     overall_modulo: u64,
+    throws: Option<Vec<Throw<W>>>,
 }
 
-impl Scene {
-    fn parse(s: &str) -> Result<Scene> {
+impl<W: WorryLevel> Scene<W> {
+    fn parse(s: &str) -> Result<Scene<W>> {
         lazy_static! {
             static ref MONKEY_RE: Regex = Regex::new(r"Monkey\s\d+:\s+Starting\sitems:\s([\d, ]+)\s+Operation:\snew\s=(.+)\s*Test:\sdivisible\sby\s(\d+)\s+If\strue:\sthrow\sto\smonkey\s(\d+)\s+If\sfalse:\sthrow\sto\smonkey\s(\d+)").unwrap();
             static ref OPERATIONS_RE: Regex = Regex::new(r"(\w+)\s([+\-\*/])\s(\w+)").unwrap();
         }
 
         let mut monkeys = vec![];
-        for cap in MONKEY_RE.captures_iter(s) {
+        for (i, cap) in MONKEY_RE.captures_iter(s).enumerate() {
+            if cap[3].parse::<u64>()? == 0 {
+                return Err(anyhow!("Monkey {i} tests divisibility by 0"));
+            }
             monkeys.push(Monkey {
                 items: cap[1]
                     .split(',')
-                    .map(|i|i.trim().parse::<u64>().unwrap())
-                    .map(|x|ItemWorryLevel(x))
-                    .collect(),
-                operation: parse_operation(&cap[2]),
+                    .map(|i|i.trim().parse::<W>().map(ItemWorryLevel))
+                    .collect::<Result<_, _>>()?,
+                operation: parse_operation(&cap[2])?,
                 test_divisibility_to: cap[3].parse()?,
                 throw_to_if_divisible: cap[4].parse()?,
                 throw_to_if_not_divisible: cap[5].parse()?, 
@@ -83,63 +234,75 @@ impl Scene {
             });
         }
 
-        let overall_modulo = monkeys.iter().map(|monkey| monkey.test_divisibility_to).fold(1, |a, b| a * b);
+        let overall_modulo = monkeys.iter()
+            .map(|monkey| monkey.test_divisibility_to)
+            .try_fold(1, lcm)
+            .ok_or_else(|| anyhow!("Least common multiple of the divisors doesn't fit into 64 bits"))?;
+
+        return Ok(Scene { monkeys, overall_modulo, throws: None });
 
-        return Ok(Scene { monkeys, overall_modulo, });
+        fn lcm(a: u64, b: u64) -> Option<u64> {
+            let (mut x, mut y) = (a, b);
+            while y != 0 {
+                (x, y) = (y, x % y);
+            }
+            (a / x).checked_mul(b)
+        }
 
-        fn parse_operation(operation: &str) -> Box<dyn Fn(u64) -> u64> {
+        fn parse_operation<W: WorryLevel>(operation: &str) -> Result<Operation<W>> {
             let parts: Vec<&str> = operation.split_whitespace().collect();
-            match parts.as_slice() {
+            let operation: Operation<W> = match parts.as_slice() {
                 ["old", "*", "old"] => {
-                    Box::new(move |x| x * x)
+                    Box::new(move |x| x.checked_mul(x))
                 }
                 ["old", "*", factor] => {
-                    let factor = factor.parse::<u64>().unwrap();
-                    Box::new(move |x| x * factor)
+                    let factor = factor.parse::<W>()?;
+                    Box::new(move |x| x.checked_mul(&factor))
                 }
                 ["old", "+", addend] => {
-                    let addend = addend.parse::<u64>().unwrap();
-                    Box::new(move |x| x + addend)
+                    let addend = addend.parse::<W>()?;
+                    Box::new(move |x| x.checked_add(&addend))
                 }
-                _ => panic!("Unknown operation"),
-            }
+                _ => return Err(anyhow!("Unknown operation {operation}")),
+            };
+            Ok(operation)
         }
     }
 
-    fn run_round_with_releif(&mut self) {
+    fn run_round(&mut self, relief: Relief) -> Result<()> {
         for i in 0..self.monkeys.len() {
             while let Some(mut item) = self.monkeys[i].items.pop_front() {
                 let monkey = &mut self.monkeys[i];
                 monkey.inspected_items_count += 1;
 
-                item.0 = (monkey.operation)(item.0);
-                item.0 = (item.0 as f64 / 3.0).floor() as u64;
-                let throw_to_monkey = if item.0 % monkey.test_divisibility_to == 0 { monkey.throw_to_if_divisible } 
+                item.0 = (monkey.operation)(&item.0)
+                    .ok_or_else(|| anyhow!("Worry level {} overflows at monkey {i}", item.0))?;
+                item.0 = match relief {
+                    Relief::DivideBy(k) => item.0.div_u64(k),
+                    Relief::ModuloLcm => W::from_u64(item.0.rem_u64(self.overall_modulo)),
+                    Relief::None => item.0,
+                };
+                let throw_to_monkey = if item.0.rem_u64(monkey.test_divisibility_to) == 0 { monkey.throw_to_if_divisible } 
                                       else { monkey.throw_to_if_not_divisible };
 
+                self.record_throw(i, throw_to_monkey, &item);
                 self.monkeys[throw_to_monkey].items.push_back(item);
             }
         }
+        Ok(())
     }
 
-    fn run_round_no_releif(&mut self) {
-        for i in 0..self.monkeys.len() {
-            while let Some(mut item) = self.monkeys[i].items.pop_front() {
-                let monkey = &mut self.monkeys[i];
-                monkey.inspected_items_count += 1;
-
-                item.0 = (monkey.operation)(item.0) % self.overall_modulo;
-                let throw_to_monkey = if item.0 % monkey.test_divisibility_to == 0 { monkey.throw_to_if_divisible } 
-                                      else { monkey.throw_to_if_not_divisible };
+    fn record_throws(&mut self) {
+        self.throws = Some(vec![]);
+    }
 
-                self.monkeys[throw_to_monkey].items.push_back(item);
-            }
-        }
+    fn take_throws(&mut self) -> Vec<Throw<W>> {
+        self.throws.as_mut().map(std::mem::take).unwrap_or_default()
     }
 
-    fn get_most_active_monkeys(&self) -> Vec<&Monkey> {
-        let mut monkeys_refs = self.monkeys.iter().collect::<Vec<_>>();
-        monkeys_refs.sort_unstable_by(|a, b| b.inspected_items_count.cmp(&a.inspected_items_count));
-        monkeys_refs
+    fn record_throw(&mut self, from_monkey: usize, to_monkey: usize, item: &ItemWorryLevel<W>) {
+        if let Some(throws) = &mut self.throws {
+            throws.push(Throw { from_monkey, to_monkey, worry_level: item.0.clone() });
+        }
     }
 }
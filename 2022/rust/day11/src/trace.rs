@@ -0,0 +1,76 @@
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use anyhow::{Result, anyhow};
+
+use crate::{Relief, Scene, Throw};
+use crate::worry::WorryLevel;
+
+#[derive(Clone, Copy)]
+enum Format {
+    Csv,
+    JsonLines,
+}
+
+pub struct TraceWriter {
+    format: Format,
+    with_throws: bool,
+    out: BufWriter<fs::File>,
+}
+
+impl TraceWriter {
+    pub fn create(path: &str, with_throws: bool) -> Result<Self> {
+        let format = match Path::new(path).extension().and_then(|x| x.to_str()) {
+            Some("csv") => Format::Csv,
+            Some("jsonl") | Some("json") => Format::JsonLines,
+            _ => return Err(anyhow!("Unknown trace format of {path}, expected .csv or .jsonl")),
+        };
+
+        let mut writer = TraceWriter { format, with_throws, out: BufWriter::new(fs::File::create(path)?) };
+        if let Format::Csv = format {
+            writeln!(writer.out, "relief,round,event,monkey,inspected_items_count,items,worry_level,to_monkey")?;
+        }
+        Ok(writer)
+    }
+
+    pub fn run<W: WorryLevel>(&mut self, scene: &mut Scene<W>, relief: Relief, rounds: usize) -> Result<()> {
+        if self.with_throws {
+            scene.record_throws();
+        }
+
+        self.write_round(scene, relief, 0, &[])?;
+        for round in 1..=rounds {
+            scene.run_round(relief)?;
+            let throws = scene.take_throws();
+            self.write_round(scene, relief, round, &throws)?;
+        }
+
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn write_round<W: WorryLevel>(&mut self, scene: &Scene<W>, relief: Relief, round: usize, throws: &[Throw<W>]) -> Result<()> {
+        for throw in throws {
+            match self.format {
+                Format::Csv => writeln!(self.out, "{relief},{round},throw,{},,,{},{}",
+                    throw.from_monkey, throw.worry_level, throw.to_monkey)?,
+                Format::JsonLines => writeln!(self.out,
+                    r#"{{"relief":"{relief}","round":{round},"event":"throw","monkey":{},"worry_level":{},"to_monkey":{}}}"#,
+                    throw.from_monkey, throw.worry_level, throw.to_monkey)?,
+            }
+        }
+
+        for (i, monkey) in scene.monkeys.iter().enumerate() {
+            let items = monkey.items.iter().map(|x| x.0.to_string()).collect::<Vec<_>>();
+            match self.format {
+                Format::Csv => writeln!(self.out, "{relief},{round},snapshot,{i},{},{},,",
+                    monkey.inspected_items_count, items.join(" "))?,
+                Format::JsonLines => writeln!(self.out,
+                    r#"{{"relief":"{relief}","round":{round},"event":"snapshot","monkey":{i},"inspected_items_count":{},"items":[{}]}}"#,
+                    monkey.inspected_items_count, items.join(","))?,
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,63 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+use num_bigint::BigUint;
+
+pub trait WorryLevel: Clone + Display + 'static + FromStr<Err: Error + Send + Sync + 'static> {
+    fn from_u64(value: u64) -> Self;
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+    fn div_u64(&self, divisor: u64) -> Self;
+    fn rem_u64(&self, divisor: u64) -> u64;
+}
+
+macro_rules! impl_worry_level_for_primitive {
+    ($t:ty) => {
+        impl WorryLevel for $t {
+            fn from_u64(value: u64) -> Self {
+                value as $t
+            }
+
+            fn checked_add(&self, rhs: &Self) -> Option<Self> {
+                <$t>::checked_add(*self, *rhs)
+            }
+
+            fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+                <$t>::checked_mul(*self, *rhs)
+            }
+
+            fn div_u64(&self, divisor: u64) -> Self {
+                self / divisor as $t
+            }
+
+            fn rem_u64(&self, divisor: u64) -> u64 {
+                (self % divisor as $t) as u64
+            }
+        }
+    };
+}
+
+impl_worry_level_for_primitive!(u64);
+impl_worry_level_for_primitive!(u128);
+
+impl WorryLevel for BigUint {
+    fn from_u64(value: u64) -> Self {
+        BigUint::from(value)
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+
+    fn div_u64(&self, divisor: u64) -> Self {
+        self / divisor
+    }
+
+    fn rem_u64(&self, divisor: u64) -> u64 {
+        (self % divisor).iter_u64_digits().next().unwrap_or(0)
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+use anyhow::{Result, anyhow};
+
+use crate::rope::{RopeCargo, Rng};
+use crate::{Cargo, Command, Crane, ShipBox, option_value};
+
+// Crates spread over the stacks at random, then random moves of up to a whole stack.
+fn synthetic(stacks_count: usize, crates_count: usize, moves_count: usize, rng: &mut Rng) -> (Cargo, Vec<Command>) {
+    let mut stacks = vec![vec![]; stacks_count];
+    for i in 0..crates_count {
+        stacks[rng.below(stacks_count)].push(ShipBox(label(i)));
+    }
+
+    let mut heights = stacks.iter().map(|x| x.len()).collect::<Vec<_>>();
+    let mut commands = vec![];
+    for line in 0..moves_count {
+        let from_stack = rng.below(stacks_count);
+        if heights[from_stack] == 0 {
+            continue;
+        }
+        let to_stack = rng.below(stacks_count);
+        let amount = 1 + rng.below(heights[from_stack]);
+        heights[from_stack] -= amount;
+        heights[to_stack] += amount;
+        commands.push(Command { from_stack, to_stack, amount, line: line + 1 });
+    }
+    (Cargo { stacks }, commands)
+}
+
+// A, B, ..., Z, AA, AB, ... like spreadsheet columns.
+fn label(mut index: usize) -> String {
+    let mut label = vec![];
+    loop {
+        label.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    label.reverse();
+    String::from_utf8(label).unwrap()
+}
+
+fn timed<T>(f: impl FnOnce() -> Result<T>) -> Result<(T, Duration)> {
+    let start = Instant::now();
+    let result = f()?;
+    Ok((result, start.elapsed()))
+}
+
+pub fn run(args: &[String]) -> Result<()> {
+    let stacks_count = option_value(args, "--stacks").map(|x| x.parse()).transpose()?.unwrap_or(9);
+    let crates_count = option_value(args, "--crates").map(|x| x.parse()).transpose()?.unwrap_or(1_000_000);
+    let moves_count = option_value(args, "--moves").map(|x| x.parse()).transpose()?.unwrap_or(2_000);
+    let seed = option_value(args, "--seed").map(|x| x.parse()).transpose()?.unwrap_or(2022);
+    if stacks_count == 0 {
+        return Err(anyhow!("--stacks needs at least one stack"));
+    }
+
+    let (cargo, commands) = synthetic(stacks_count, crates_count, moves_count, &mut Rng::new(seed));
+    let moved: usize = commands.iter().map(|x| x.amount).sum();
+    println!("{crates_count} crates on {stacks_count} stacks, {} moves of {moved} crates in total", commands.len());
+
+    for crane in [Crane::CrateMover9000, Crane::CrateMover9001] {
+        let (vectors, vectors_time) = timed(|| {
+            let mut cargo = cargo.clone();
+            for command in commands.iter() {
+                crane.apply(&mut cargo, command)?;
+            }
+            Ok(cargo)
+        })?;
+        let (mut rope, build_time) = timed(|| Ok(RopeCargo::new(&cargo)))?;
+        let (_, rope_time) = timed(|| {
+            for command in commands.iter() {
+                rope.apply(crane, command)?;
+            }
+            Ok(())
+        })?;
+
+        if rope.to_cargo() != vectors {
+            return Err(anyhow!("{crane} left different stacks with the rope"));
+        }
+        println!("{crane}: vectors {:.3} s, rope {:.3} s (building it {:.3} s), same stacks, top {}",
+            vectors_time.as_secs_f64(), rope_time.as_secs_f64(), build_time.as_secs_f64(), rope.top_of_stacks());
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use anyhow::{Result, anyhow};
+
+use crate::ShipBox;
+
+// Stack columns come from the row of stack numbers: every crate goes to the stack whose
+// number is closest to the middle of its brackets. Positions are counted in characters and
+// doubled so the middle of a span is always a whole number.
+pub fn parse(rows: &[String], numbers: &str) -> Result<Vec<Vec<ShipBox>>> {
+    let centers = columns(numbers)?;
+    let mut stacks: Vec<Vec<ShipBox>> = vec![vec![]; centers.len()];
+    // Stacks that had a crate in every row read so far, going up from the bottom.
+    let mut supported = vec![true; centers.len()];
+
+    for (i, row) in rows.iter().enumerate().rev() {
+        let error = |reason: String| anyhow!("Line {}: {reason}", i + 1);
+        let mut filled = vec![false; centers.len()];
+        for (start, end, label) in crates(row).map_err(error)? {
+            let middle = start + end - 1;
+            let stack = (0..centers.len())
+                .min_by_key(|&k| centers[k].abs_diff(middle))
+                .ok_or_else(|| error("there are no stacks".to_owned()))?;
+            if filled[stack] {
+                return Err(error(format!("more than one crate is above stack {}", stack + 1)));
+            }
+            if !supported[stack] {
+                return Err(error(format!("crate [{label}] floats above an empty place in stack {}", stack + 1)));
+            }
+            filled[stack] = true;
+            stacks[stack].push(ShipBox(label));
+        }
+        for (supported, filled) in supported.iter_mut().zip(filled) {
+            *supported &= filled;
+        }
+    }
+
+    Ok(stacks)
+}
+
+pub fn is_numbers_row(line: &str) -> bool {
+    !line.trim().is_empty() && line.split_whitespace().all(|x| x.parse::<usize>().is_ok())
+}
+
+// Doubled middle position of every stack number, which have to count up from 1.
+fn columns(numbers: &str) -> Result<Vec<usize>> {
+    let mut centers = vec![];
+    for (start, end, token) in tokens(numbers) {
+        if token.parse::<usize>().ok() != Some(centers.len() + 1) {
+            return Err(anyhow!("Stack numbers have to count up from 1, found {token} as number {}", centers.len() + 1));
+        }
+        centers.push(start + end - 1);
+    }
+    Ok(centers)
+}
+
+fn tokens(line: &str) -> Vec<(usize, usize, String)> {
+    let chars = line.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() {
+            i += 1;
+        }
+        tokens.push((start, i, chars[start..i].iter().collect()));
+    }
+    tokens
+}
+
+// Start and end (exclusive) character positions of every `[label]` in a drawing row.
+fn crates(row: &str) -> Result<Vec<(usize, usize, String)>, String> {
+    let chars = row.chars().collect::<Vec<_>>();
+    let mut crates = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '[' => {
+                let end = (i + 1..chars.len())
+                    .find(|&j| chars[j] == ']')
+                    .ok_or_else(|| format!("crate at column {} has no closing bracket", i + 1))?;
+                let label = chars[i + 1..end].iter().collect::<String>();
+                if label.trim().is_empty() {
+                    return Err(format!("crate at column {} has no label", i + 1));
+                }
+                crates.push((i, end + 1, label));
+                i = end + 1;
+            },
+            c => return Err(format!("unexpected character '{c}' at column {}", i + 1)),
+        }
+    }
+    Ok(crates)
+}
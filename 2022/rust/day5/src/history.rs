@@ -0,0 +1,100 @@
+use anyhow::{Result, anyhow};
+
+use crate::{Cargo, Command, Crane};
+
+const SNAPSHOT_INTERVAL: usize = 64;
+
+// Cargo after any number of applied commands. Both cranes can put crates back by moving
+// them the other way, so undo and redo are single moves and jumps restore the closest
+// snapshot before replaying the rest.
+pub struct History<'a> {
+    crane: Crane,
+    commands: &'a [Command],
+    snapshots: Vec<Cargo>,
+    cargo: Cargo,
+    position: usize,
+}
+
+impl<'a> History<'a> {
+    pub fn new(cargo: Cargo, commands: &'a [Command], crane: Crane) -> Result<Self> {
+        let mut snapshots = vec![cargo.clone()];
+        let mut current = cargo.clone();
+        for (i, command) in commands.iter().enumerate() {
+            crane.apply(&mut current, command)?;
+            if (i + 1).is_multiple_of(SNAPSHOT_INTERVAL) {
+                snapshots.push(current.clone());
+            }
+        }
+        Ok(History { crane, commands, snapshots, cargo, position: 0 })
+    }
+
+    pub fn cargo(&self) -> &Cargo {
+        &self.cargo
+    }
+
+    // Number of commands applied to the current cargo.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn redo(&mut self) -> Result<bool> {
+        let Some(command) = self.commands.get(self.position) else {
+            return Ok(false);
+        };
+        self.crane.apply(&mut self.cargo, command)?;
+        self.position += 1;
+        Ok(true)
+    }
+
+    pub fn undo(&mut self) -> Result<bool> {
+        if self.position == 0 {
+            return Ok(false);
+        }
+        let command = &self.commands[self.position - 1];
+        let reverse = Command { from_stack: command.to_stack, to_stack: command.from_stack, ..*command };
+        self.crane.apply(&mut self.cargo, &reverse)?;
+        self.position -= 1;
+        Ok(true)
+    }
+
+    pub fn jump(&mut self, position: usize) -> Result<()> {
+        if position > self.commands.len() {
+            return Err(anyhow!("There are only {} commands", self.commands.len()));
+        }
+        self.cargo = self.snapshots[position / SNAPSHOT_INTERVAL].clone();
+        self.position = position / SNAPSHOT_INTERVAL * SNAPSHOT_INTERVAL;
+        while self.position < position {
+            self.redo()?;
+        }
+        Ok(())
+    }
+}
+
+// Number of the first command after which the two cranes leave different stacks.
+pub fn first_divergence(cargo: &Cargo, commands: &[Command]) -> Result<Option<usize>> {
+    let mut one_by_one = cargo.clone();
+    let mut all_at_once = cargo.clone();
+    for (i, command) in commands.iter().enumerate() {
+        Crane::CrateMover9000.apply(&mut one_by_one, command)?;
+        Crane::CrateMover9001.apply(&mut all_at_once, command)?;
+        if one_by_one != all_at_once {
+            return Ok(Some(i + 1));
+        }
+    }
+    Ok(None)
+}
+
+// One line per stack that differs, listing the crates from the bottom.
+pub fn diff(left: &Cargo, right: &Cargo) -> Vec<String> {
+    let crates = |cargo: &Cargo, i: usize| {
+        cargo.stacks.get(i).map_or(String::new(), |stack| stack.iter().map(|x| format!("[{}]", x.0)).collect::<Vec<_>>().join(" "))
+    };
+    (0..left.stacks.len().max(right.stacks.len()))
+        .filter(|&i| left.stacks.get(i) != right.stacks.get(i))
+        .map(|i| format!("stack {}: {} | {}", i + 1, crates(left, i), crates(right, i)))
+        .collect()
+}
@@ -1,135 +1,387 @@
-use std::io::{self, BufRead};
+mod bench;
+mod drawing;
+mod history;
+mod render;
+mod rope;
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
 use regex::Regex;
 use anyhow::{Result, anyhow};
 
 fn main() {
-    let stdin = io::stdin();
-    let lines = stdin.lock().lines();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(|x| x.as_str()) {
+        Some("replay") => replay(&args[1..]),
+        Some("bench") => bench::run(&args[1..]),
+        command => load(io::stdin().lock().lines()).and_then(|(cargo, commands)| match command {
+            Some("part_one") => part_one(cargo, &commands, &args[1..]),
+            Some("render") => render::run(cargo, &commands, &args[1..]),
+            Some("diverge") => diverge(cargo, &commands, &args[1..]),
+            _ => part_two(cargo, &commands, &args),
+        }),
+    };
 
-    if let Err(err) = part_two(lines) {
+    if let Err(err) = result {
         println!("Input has error: {:?}", err);
     }
 }
 
-fn part_one(lines: io::Lines<io::StdinLock<'_>>) -> Result<()> {
-    let (cargo_input, stacks_count, commands) = parse_input(lines)?;
-    let mut cargo = Cargo::from_input(&cargo_input, stacks_count);
+fn load(lines: impl Iterator<Item = io::Result<String>>) -> Result<(Cargo, Vec<Command>)> {
+    let (cargo_input, stack_numbers, commands) = parse_input(lines)?;
+    let cargo = Cargo::from_input(&cargo_input, &stack_numbers)?;
+    cargo.validate(&commands)?;
+    Ok((cargo, commands))
+}
 
-    for command in commands {
-        for _ in 0..command.amount {
-            cargo.move_box(command.from_stack, command.to_stack);
-        }
-    }
+fn part_one(cargo: Cargo, commands: &[Command], args: &[String]) -> Result<()> {
+    println!("The result is: {}", top_after_moves(cargo, commands, Crane::CrateMover9000, args)?);
+    Ok(())
+}
 
-    println!("The result is: {}", cargo.top_of_stacks());
+fn part_two(cargo: Cargo, commands: &[Command], args: &[String]) -> Result<()> {
+    println!("The result is: {}", top_after_moves(cargo, commands, Crane::CrateMover9001, args)?);
     Ok(())
 }
 
-fn part_two(lines: io::Lines<io::StdinLock<'_>>) -> Result<()> {
-    let (cargo_input, stacks_count, commands) = parse_input(lines)?;
-    let mut cargo = Cargo::from_input(&cargo_input, stacks_count);
+// With --rope the stacks are kept as treaps, which is faster for huge inputs.
+fn top_after_moves(mut cargo: Cargo, commands: &[Command], crane: Crane, args: &[String]) -> Result<String> {
+    if args.iter().any(|x| x == "--rope") {
+        let mut rope = rope::RopeCargo::new(&cargo);
+        for command in commands {
+            rope.apply(crane, command)?;
+        }
+        return Ok(rope.top_of_stacks());
+    }
 
     for command in commands {
-        cargo.move_boxes(command.from_stack, command.to_stack, command.amount);
+        crane.apply(&mut cargo, command)?;
     }
+    Ok(cargo.top_of_stacks())
+}
+
+// Differences between the two cranes after command k given with --at, otherwise after the first command where they differ.
+fn diverge(cargo: Cargo, commands: &[Command], args: &[String]) -> Result<()> {
+    let position = match option_value(args, "--at") {
+        Some(position) => position.parse()?,
+        None => match history::first_divergence(&cargo, commands)? {
+            Some(position) => {
+                println!("Cranes diverge after command {position} on line {} ({})", commands[position - 1].line, commands[position - 1]);
+                position
+            },
+            None => {
+                println!("Both cranes leave the same stacks after every command");
+                return Ok(());
+            },
+        },
+    };
 
-    println!("The result is: {}", cargo.top_of_stacks());
+    let mut one_by_one = history::History::new(cargo.clone(), commands, Crane::CrateMover9000)?;
+    let mut all_at_once = history::History::new(cargo, commands, Crane::CrateMover9001)?;
+    one_by_one.jump(position)?;
+    all_at_once.jump(position)?;
+    print_diff(one_by_one.cargo(), all_at_once.cargo());
     Ok(())
 }
 
-fn parse_input(lines: io::Lines<io::StdinLock<'_>>) -> Result<(Vec<String>, usize, Vec<Command>)> {
+fn print_diff(one_by_one: &Cargo, all_at_once: &Cargo) {
+    let lines = history::diff(one_by_one, all_at_once);
+    if lines.is_empty() {
+        println!("No differences");
+    }
+    for line in lines {
+        println!("{line}");
+    }
+}
+
+fn replay(args: &[String]) -> Result<()> {
+    let path = args.first().ok_or_else(|| anyhow!("Usage: day5 replay <input file> [--crane 9000|9001]"))?;
+    let crane = option_value(args, "--crane").map(|x| x.parse()).transpose()?.unwrap_or(Crane::CrateMover9001);
+    let (cargo, commands) = load(fs::read_to_string(path)?.lines().map(|x| Ok(x.to_owned())))?;
+    let other_crane = match crane {
+        Crane::CrateMover9000 => Crane::CrateMover9001,
+        Crane::CrateMover9001 => Crane::CrateMover9000,
+    };
+    let mut history = history::History::new(cargo.clone(), &commands, crane)?;
+    let mut other = history::History::new(cargo, &commands, other_crane)?;
+
+    println!("Loaded {} commands for the {crane}. Commands: redo [n], undo [n], jump <k>, print, diff, quit", commands.len());
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(after {}/{}) > ", history.position(), history.len());
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let line = line?;
+        let parts = line.split_whitespace().collect::<Vec<_>>();
+
+        match parts.as_slice() {
+            ["redo" | "r"] => {
+                step(history.redo()?, &history);
+            },
+            ["redo" | "r", n] => match n.parse::<usize>() {
+                Ok(n) => {
+                    for _ in 0..n {
+                        if !step(history.redo()?, &history) {
+                            break;
+                        }
+                    }
+                },
+                Err(_) => println!("Not a number of commands: {n}"),
+            },
+            ["undo" | "u"] => {
+                step(history.undo()?, &history);
+            },
+            ["undo" | "u", n] => match n.parse::<usize>() {
+                Ok(n) => {
+                    for _ in 0..n {
+                        if !step(history.undo()?, &history) {
+                            break;
+                        }
+                    }
+                },
+                Err(_) => println!("Not a number of commands: {n}"),
+            },
+            ["jump" | "j", k] => match k.parse() {
+                Ok(k) => match history.jump(k) {
+                    Ok(()) => print!("{}", history.cargo().draw(0)),
+                    Err(err) => println!("{err}"),
+                },
+                Err(_) => println!("Not a command number: {k}"),
+            },
+            ["print" | "p"] => print!("{}", history.cargo().draw(0)),
+            ["diff" | "d"] => {
+                other.jump(history.position())?;
+                print_diff(history.cargo(), other.cargo());
+            },
+            ["quit" | "q"] => return Ok(()),
+            [] => {},
+            _ => println!("Unknown command: {line}"),
+        }
+    }
+
+    // Prints where the history got to and passes on whether it moved at all.
+    fn step(moved: bool, history: &history::History) -> bool {
+        match (moved, history.position()) {
+            (false, 0) => println!("Already at the initial state"),
+            (false, _) => println!("All commands are applied"),
+            (true, position) => println!("After command {position}: {}", history.cargo().top_of_stacks()),
+        }
+        moved
+    }
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|x| x == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|x| x.as_str())
+}
+
+// Drawing rows up to the row of stack numbers, then the move commands.
+fn parse_input(lines: impl Iterator<Item = io::Result<String>>) -> Result<(Vec<String>, String, Vec<Command>)> {
     let mut cargo_input: Vec<String> = vec![];
-    let mut stacks_count: usize = 0;
+    let mut stack_numbers: Option<String> = None;
     let mut commands: Vec<Command> = vec![];
 
-    for line in lines {
-        let line = line.unwrap();
-        if line.starts_with('[') {
-            cargo_input.push(line);
+    let move_re = Regex::new(r"move (\d+) from (\d+) to (\d+)").unwrap();
+    for (i, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
         }
-        else if line.starts_with(" 1") {
-            let re = Regex::new(r"\b(\d+)\b").unwrap();
-            let last_match = re.captures_iter(&line).last().ok_or_else(||anyhow!("No matches of number"))?;
-
-            stacks_count = last_match
-                .get(1).ok_or_else(||anyhow!("No number captured"))?
-                .as_str()
-                .parse()?;
+        else if stack_numbers.is_none() {
+            if drawing::is_numbers_row(&line) {
+                stack_numbers = Some(line);
+            } else {
+                cargo_input.push(line);
+            }
         }
         else if line.starts_with("move") {
-            let re = Regex::new(r"move (\d+) from (\d+) to (\d+)").unwrap();
-            let command_match = re.captures(&line).ok_or_else(||anyhow!("No regex match for move line"))?;
+            let command_match = move_re.captures(&line).ok_or_else(||anyhow!("Line {}: No regex match for move line", i + 1))?;
 
             let amount = command_match.get(1).ok_or_else(||anyhow!("No amount part catured"))?
                 .as_str().parse()?;
 
-            let from: i32 = command_match.get(2).ok_or_else(||anyhow!("No from_stack part catured"))?
-                .as_str().parse()?;
-            let from = from - 1;
-
-            let to: i32 = command_match.get(3).ok_or_else(||anyhow!("No from_stack part catured"))?
-                .as_str().parse()?;
-            let to = to - 1;
+            // Stacks are numbered from 1 in the input.
+            let stack_index = |group: usize| -> Result<usize> {
+                let number: usize = command_match.get(group).ok_or_else(||anyhow!("No stack part catured"))?
+                    .as_str().parse()?;
+                number.checked_sub(1).ok_or_else(|| anyhow!("Line {}: stack numbers start at 1", i + 1))
+            };
 
-            commands.push(Command { 
-                from_stack: from as usize, 
-                to_stack: to as usize, 
-                amount 
+            commands.push(Command {
+                from_stack: stack_index(2)?,
+                to_stack: stack_index(3)?,
+                amount,
+                line: i + 1,
             });
         }
+        else {
+            return Err(anyhow!("Line {}: expected a move command, found '{line}'", i + 1));
+        }
     }
-    Ok((cargo_input, stacks_count, commands))
+
+    let stack_numbers = stack_numbers.ok_or_else(|| anyhow!("No row of stack numbers below the drawing"))?;
+    Ok((cargo_input, stack_numbers, commands))
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 struct Cargo {
     stacks: Vec<Vec<ShipBox>>
 }
 
 impl Cargo {
-    fn from_input(input: &Vec<String>, stacks_count: usize) -> Self {
-        let mut cargo = Cargo{stacks: vec![vec![]; stacks_count]};
-
-        for line in input.iter().rev().map(|l| l.as_str()) {
-            for i in (1..line.len()).step_by(4) {
-                let letter_or_space = line.as_bytes()[i] as char;
-                if letter_or_space != ' ' {
-                    let stack_index = i / 4;
-                    cargo.stacks[stack_index].push(ShipBox(letter_or_space));
+    fn from_input(input: &[String], stack_numbers: &str) -> Result<Self> {
+        Ok(Cargo { stacks: drawing::parse(input, stack_numbers)? })
+    }
+
+    // Checks every command before any of them runs. Both cranes change the stack heights
+    // the same way, so following the heights is enough.
+    fn validate(&self, commands: &[Command]) -> Result<()> {
+        let mut heights = self.stacks.iter().map(|x| x.len()).collect::<Vec<_>>();
+        for (i, command) in commands.iter().enumerate() {
+            let error = |reason: String| anyhow!("Command {} on line {} ({command}): {reason}", i + 1, command.line);
+            for stack in [command.from_stack, command.to_stack] {
+                if stack >= heights.len() {
+                    return Err(error(format!("stack {} doesn't exist, there are {} stacks", stack + 1, heights.len())));
                 }
             }
+            if heights[command.from_stack] < command.amount {
+                return Err(error(format!("stack {} has only {} crates", command.from_stack + 1, heights[command.from_stack])));
+            }
+            heights[command.from_stack] -= command.amount;
+            heights[command.to_stack] += command.amount;
         }
+        Ok(())
+    }
 
-        cargo
+    fn stack(&mut self, index: usize) -> Result<&mut Vec<ShipBox>> {
+        let count = self.stacks.len();
+        self.stacks.get_mut(index).ok_or_else(|| anyhow!("Stack {} doesn't exist, there are {count} stacks", index + 1))
     }
 
-    fn move_box(&mut self, from: usize, to: usize) {
-        let ship_box = self.stacks[from].pop().ok_or_else(||anyhow!("No boxes left in stack to move")).unwrap();
+    fn move_box(&mut self, from: usize, to: usize) -> Result<()> {
+        self.stack(to)?;
+        let ship_box = self.stack(from)?.pop().ok_or_else(||anyhow!("No boxes left in stack {} to move", from + 1))?;
         self.stacks[to].push(ship_box);
+        Ok(())
     }
-    
-    fn move_boxes(&mut self, from: usize, to: usize, amount: usize) {
-        let from_stack = &mut self.stacks[from];
-        let to_move = from_stack.split_off(from_stack.len() - amount);
+
+    fn move_boxes(&mut self, from: usize, to: usize, amount: usize) -> Result<()> {
+        self.stack(to)?;
+        let from_stack = self.stack(from)?;
+        let start = from_stack.len().checked_sub(amount)
+            .ok_or_else(|| anyhow!("Stack {} has only {} boxes, can't move {amount}", from + 1, from_stack.len()))?;
+        let to_move = from_stack.split_off(start);
 
         self.stacks[to].extend(to_move);
+        Ok(())
+    }
+
+    fn height(&self) -> usize {
+        self.stacks.iter().map(|x| x.len()).max().unwrap_or(0)
+    }
+
+    // Same drawing as the puzzle input, padded to the given number of rows so frames line up.
+    // Columns fit the longest crate label, stack numbers may also use the space between columns.
+    fn draw(&self, height: usize) -> String {
+        let width = self.stacks.iter()
+            .flatten()
+            .map(|x| x.0.chars().count())
+            .chain([self.stacks.len().to_string().len().saturating_sub(1)])
+            .max()
+            .unwrap_or(1);
+        let mut drawing = String::new();
+        for row in (0..height.max(self.height())).rev() {
+            let line = self.stacks.iter()
+                .map(|stack| stack.get(row).map_or(" ".repeat(width + 2), |x| format!("[{:^width$}]", x.0)))
+                .collect::<Vec<_>>();
+            drawing.push_str(line.join(" ").trim_end());
+            drawing.push('\n');
+        }
+        let labels = (1..=self.stacks.len()).map(|x| format!("{x:^0$}", width + 2)).collect::<Vec<_>>();
+        drawing.push_str(&labels.join(" "));
+        drawing.push('\n');
+        drawing
     }
 
     fn top_of_stacks(&self) -> String {
         let mut result = String::with_capacity(self.stacks.len());
         for stack in self.stacks.iter() {
-            result.push(stack.last().ok_or_else(||anyhow!("Empty stack at the end")).unwrap().0);
+            match stack.last() {
+                Some(ship_box) => result.push_str(&ship_box.0),
+                None => result.push(EMPTY_STACK),
+            }
         }
         result
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-struct ShipBox(char);
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ShipBox(String);
+
+// Stands for a stack without crates in the top of stacks message.
+const EMPTY_STACK: char = '_';
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 struct Command {
     from_stack: usize,
     to_stack: usize,
     amount: usize,
+    line: usize,
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.amount, self.from_stack + 1, self.to_stack + 1)
+    }
+}
+
+// CrateMover 9000 moves crates one at a time, 9001 picks up all of them at once keeping their order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Crane {
+    CrateMover9000,
+    CrateMover9001,
+}
+
+impl Crane {
+    fn apply(self, cargo: &mut Cargo, command: &Command) -> Result<()> {
+        match self {
+            Crane::CrateMover9000 => {
+                for _ in 0..command.amount {
+                    cargo.move_box(command.from_stack, command.to_stack)?;
+                }
+                Ok(())
+            },
+            Crane::CrateMover9001 => cargo.move_boxes(command.from_stack, command.to_stack, command.amount),
+        }
+    }
+}
+
+impl FromStr for Crane {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "9000" => Ok(Crane::CrateMover9000),
+            "9001" => Ok(Crane::CrateMover9001),
+            _ => Err(anyhow!("Unknown crane '{s}', expected 9000 or 9001")),
+        }
+    }
+}
+
+impl fmt::Display for Crane {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Crane::CrateMover9000 => write!(f, "CrateMover 9000"),
+            Crane::CrateMover9001 => write!(f, "CrateMover 9001"),
+        }
+    }
 }
@@ -0,0 +1,43 @@
+use std::thread;
+use std::time::Duration;
+use anyhow::{Result, anyhow};
+
+use crate::{Cargo, Command, Crane, option_value};
+
+pub fn run(mut cargo: Cargo, commands: &[Command], args: &[String]) -> Result<()> {
+    let crane: Crane = option_value(args, "--crane").map(|x| x.parse()).transpose()?.unwrap_or(Crane::CrateMover9001);
+    let every: usize = option_value(args, "--every").map(|x| x.parse()).transpose()?.unwrap_or(1);
+    let animate = args.iter().any(|x| x == "--animate");
+    let delay = option_value(args, "--delay").map(|x| x.parse()).transpose()?.unwrap_or(200);
+    if every == 0 {
+        return Err(anyhow!("--every needs a positive number of commands"));
+    }
+
+    // The moves are simulated once up front so every frame has the same number of rows.
+    let mut highest = cargo.clone();
+    let mut height = highest.height();
+    for command in commands {
+        crane.apply(&mut highest, command)?;
+        height = height.max(highest.height());
+    }
+
+    if !animate {
+        println!("== Initial state ({crane}) ==\n\n{}", cargo.draw(height));
+    }
+    for (i, command) in commands.iter().enumerate() {
+        crane.apply(&mut cargo, command)?;
+        if !(i + 1).is_multiple_of(every) && i + 1 != commands.len() {
+            continue;
+        }
+        if animate {
+            print!("\x1b[2J\x1b[H== {command} ({}/{}) ==\n\n{}", i + 1, commands.len(), cargo.draw(height));
+            thread::sleep(Duration::from_millis(delay));
+        } else {
+            println!("== {command} ({}/{}) ==\n\n{}", i + 1, commands.len(), cargo.draw(height));
+        }
+    }
+
+    println!("== Final state ==\n\n{}", cargo.draw(0));
+    println!("Top of stacks: {}", cargo.top_of_stacks());
+    Ok(())
+}
@@ -0,0 +1,188 @@
+use anyhow::{Result, anyhow};
+
+use crate::{Cargo, Command, Crane, EMPTY_STACK, ShipBox};
+
+// xorshift64*, enough for treap priorities and synthetic inputs.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+struct Node {
+    priority: u64,
+    size: usize,
+    // The children of this subtree are still to be swapped, all the way down.
+    reversed: bool,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+// Every stack is an implicit treap ordered from the bottom crate, with all crates in one arena
+// where node i is crate i. Moving the top k crates is one split and one merge, and the
+// CrateMover 9000 putting them down in reverse order only flips a lazy flag, so a command
+// takes O(log n) for both cranes whatever the amount.
+pub struct RopeCargo {
+    labels: Vec<ShipBox>,
+    nodes: Vec<Node>,
+    stacks: Vec<Option<usize>>,
+}
+
+impl RopeCargo {
+    pub fn new(cargo: &Cargo) -> Self {
+        let mut rng = Rng::new(0x5eed);
+        let mut rope = RopeCargo { labels: vec![], nodes: vec![], stacks: vec![] };
+        for stack in cargo.stacks.iter() {
+            let mut root = None;
+            for ship_box in stack {
+                let id = rope.nodes.len();
+                rope.labels.push(ship_box.clone());
+                rope.nodes.push(Node { priority: rng.next(), size: 1, reversed: false, left: None, right: None });
+                root = rope.merge(root, Some(id));
+            }
+            rope.stacks.push(root);
+        }
+        rope
+    }
+
+    pub fn apply(&mut self, crane: Crane, command: &Command) -> Result<()> {
+        let count = self.stacks.len();
+        for stack in [command.from_stack, command.to_stack] {
+            if stack >= count {
+                return Err(anyhow!("Stack {} doesn't exist, there are {count} stacks", stack + 1));
+            }
+        }
+        let height = self.size(self.stacks[command.from_stack]);
+        let rest = height.checked_sub(command.amount)
+            .ok_or_else(|| anyhow!("Stack {} has only {height} boxes, can't move {}", command.from_stack + 1, command.amount))?;
+        if command.from_stack == command.to_stack {
+            return Ok(());
+        }
+
+        let (bottom, top) = self.split(self.stacks[command.from_stack], rest);
+        if let (Crane::CrateMover9000, Some(top)) = (crane, top) {
+            self.nodes[top].reversed ^= true;
+        }
+        self.stacks[command.from_stack] = bottom;
+        self.stacks[command.to_stack] = self.merge(self.stacks[command.to_stack], top);
+        Ok(())
+    }
+
+    pub fn top_of_stacks(&self) -> String {
+        let mut result = String::with_capacity(self.stacks.len());
+        for &root in self.stacks.iter() {
+            match self.last(root) {
+                Some(id) => result.push_str(&self.labels[id].0),
+                None => result.push(EMPTY_STACK),
+            }
+        }
+        result
+    }
+
+    pub fn to_cargo(&self) -> Cargo {
+        let stacks = self.stacks.iter()
+            .map(|&root| {
+                let mut stack = vec![];
+                self.collect(root, false, &mut stack);
+                stack
+            })
+            .collect();
+        Cargo { stacks }
+    }
+
+    fn collect(&self, tree: Option<usize>, reversed: bool, stack: &mut Vec<ShipBox>) {
+        let Some(id) = tree else {
+            return;
+        };
+        let node = &self.nodes[id];
+        let reversed = reversed ^ node.reversed;
+        let (first, second) = if reversed { (node.right, node.left) } else { (node.left, node.right) };
+        self.collect(first, reversed, stack);
+        stack.push(self.labels[id].clone());
+        self.collect(second, reversed, stack);
+    }
+
+    // Top crate, following the pending reversals on the way down instead of applying them.
+    fn last(&self, root: Option<usize>) -> Option<usize> {
+        let mut id = root?;
+        let mut reversed = false;
+        loop {
+            reversed ^= self.nodes[id].reversed;
+            let next = if reversed { self.nodes[id].left } else { self.nodes[id].right };
+            match next {
+                Some(next) => id = next,
+                None => return Some(id),
+            }
+        }
+    }
+
+    fn size(&self, tree: Option<usize>) -> usize {
+        tree.map_or(0, |id| self.nodes[id].size)
+    }
+
+    fn update(&mut self, id: usize) {
+        self.nodes[id].size = 1 + self.size(self.nodes[id].left) + self.size(self.nodes[id].right);
+    }
+
+    fn push_down(&mut self, id: usize) {
+        if !self.nodes[id].reversed {
+            return;
+        }
+        let node = &mut self.nodes[id];
+        node.reversed = false;
+        (node.left, node.right) = (node.right, node.left);
+        for child in [node.left, node.right].into_iter().flatten() {
+            self.nodes[child].reversed ^= true;
+        }
+    }
+
+    // First `count` crates from the bottom and the rest.
+    fn split(&mut self, tree: Option<usize>, count: usize) -> (Option<usize>, Option<usize>) {
+        let Some(id) = tree else {
+            return (None, None);
+        };
+        self.push_down(id);
+        let left_size = self.size(self.nodes[id].left);
+        if count <= left_size {
+            let (bottom, top) = self.split(self.nodes[id].left, count);
+            self.nodes[id].left = top;
+            self.update(id);
+            (bottom, Some(id))
+        } else {
+            let (bottom, top) = self.split(self.nodes[id].right, count - left_size - 1);
+            self.nodes[id].right = bottom;
+            self.update(id);
+            (Some(id), top)
+        }
+    }
+
+    fn merge(&mut self, bottom: Option<usize>, top: Option<usize>) -> Option<usize> {
+        let (Some(a), Some(b)) = (bottom, top) else {
+            return bottom.or(top);
+        };
+        if self.nodes[a].priority > self.nodes[b].priority {
+            self.push_down(a);
+            self.nodes[a].right = self.merge(self.nodes[a].right, top);
+            self.update(a);
+            Some(a)
+        } else {
+            self.push_down(b);
+            self.nodes[b].left = self.merge(bottom, self.nodes[b].left);
+            self.update(b);
+            Some(b)
+        }
+    }
+}
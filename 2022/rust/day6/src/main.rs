@@ -1,45 +1,111 @@
+use std::collections::HashMap;
+use std::env;
+use std::hash::Hash;
 use std::io::{self, Read};
 
 fn main() {
     let mut input = String::new();
     let stdin = io::stdin();
     stdin.lock().read_to_string(&mut input).unwrap();
+    let input = input.trim_end_matches(['\n', '\r']);
 
-    part_two(input);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(|x| x.as_str()) {
+        Some("part_one") => part_one(input),
+        Some("markers") => markers(input, &args[1..]),
+        _ => part_two(input),
+    };
+
+    if let Err(err) = result {
+        println!("Error: {err}");
+    }
 }
 
-fn part_one(input: String) {
-    let mut last4 = [' '; 4];
+fn part_one(input: &str) -> Result<(), String> {
+    print_first_marker(input, 4)
+}
 
-    for (i, char) in input.chars().enumerate() {
-        last4[i % 4] = char;
-        if i > 3 && has_no_duplicates(&last4) {
-            println!("The result is {}", i + 1);
-            return;
-        }
-    }
+fn part_two(input: &str) -> Result<(), String> {
+    print_first_marker(input, 14)
 }
 
-fn part_two(input: String) {
-    let mut last4 = [' '; 14];
+fn print_first_marker(input: &str, window: usize) -> Result<(), String> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let position = find_marker(&chars, window).next()
+        .ok_or_else(|| format!("No {window} different characters in a row"))?;
+    println!("The result is {position}");
+    Ok(())
+}
 
-    for (i, char) in input.chars().enumerate() {
-        last4[i % 14] = char;
-        if i > 13 && has_no_duplicates(&last4) {
-            println!("The result is {}", i + 1);
-            return;
-        }
+// Every marker position for any window size, counted in characters or with --bytes in bytes.
+fn markers(input: &str, args: &[String]) -> Result<(), String> {
+    let window: usize = option_value(args, "--window")
+        .ok_or("Usage: day6 markers --window <size> [--bytes]")?
+        .parse()
+        .map_err(|err| format!("Invalid window size: {err}"))?;
+    if window == 0 {
+        return Err("Window size has to be at least 1".to_owned());
+    }
+
+    let positions: Vec<usize> = if args.iter().any(|x| x == "--bytes") {
+        find_marker(input.as_bytes(), window).collect()
+    } else {
+        find_marker(&input.chars().collect::<Vec<_>>(), window).collect()
+    };
+
+    println!("{} markers of {window} different symbols", positions.len());
+    for position in positions {
+        println!("{position}");
     }
+    Ok(())
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|x| x == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|x| x.as_str())
+}
+
+// Positions right after every window of `window` symbols that are all different, the first
+// one being the puzzle answer. Counts of the symbols in the window and how many of them
+// appear more than once are updated as it slides, so each symbol costs O(1).
+fn find_marker<T: Hash + Eq>(symbols: &[T], window: usize) -> Markers<'_, T> {
+    Markers { symbols, window, counts: HashMap::new(), repeated: 0, position: 0 }
 }
 
-fn has_no_duplicates(ascii_slice: &[char]) -> bool {
-    let mut seen = [false; 128];
+struct Markers<'a, T> {
+    symbols: &'a [T],
+    window: usize,
+    counts: HashMap<&'a T, usize>,
+    repeated: usize,
+    position: usize,
+}
+
+impl<T: Hash + Eq> Iterator for Markers<'_, T> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.position < self.symbols.len() {
+            let count = self.counts.entry(&self.symbols[self.position]).or_insert(0);
+            *count += 1;
+            if *count == 2 {
+                self.repeated += 1;
+            }
+
+            if self.position >= self.window {
+                let count = self.counts.get_mut(&self.symbols[self.position - self.window]).unwrap();
+                *count -= 1;
+                if *count == 1 {
+                    self.repeated -= 1;
+                }
+            }
 
-    for &c in ascii_slice {
-        if c as usize >= 128 || seen[c as usize] {
-            return false;
+            self.position += 1;
+            if self.position >= self.window && self.repeated == 0 {
+                return Some(self.position);
+            }
         }
-        seen[c as usize] = true;
+        None
     }
-    true
 }
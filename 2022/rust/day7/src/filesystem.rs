@@ -0,0 +1,266 @@
+use std::error::Error;
+use std::fmt;
+
+pub type NodeId = usize;
+
+pub const ROOT: NodeId = 0;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Directory { children: Vec<NodeId> },
+    File,
+    Symlink { target: String },
+}
+
+// Size of a directory is the cached size of everything inside it, updated when files are added.
+// Symbolic links are not followed, so like in `du` they don't add to any size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    pub kind: NodeKind,
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub path: String,
+    pub is_directory: bool,
+    pub link_target: Option<String>,
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathComponent {
+    Root,
+    Up,
+    Name(String),
+}
+
+// Splits a path like `/a/b`, `a/../b` or `..` into the steps to walk it, `.` is skipped.
+pub fn parse_path(path: &str) -> Vec<PathComponent> {
+    let mut components = vec![];
+    if path.starts_with('/') {
+        components.push(PathComponent::Root);
+    }
+    for name in path.split('/').filter(|x| !x.is_empty() && *x != ".") {
+        components.push(match name {
+            ".." => PathComponent::Up,
+            _ => PathComponent::Name(name.to_owned()),
+        });
+    }
+    components
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NavigationError {
+    UpFromRoot { command: usize },
+    UnknownDirectory { command: usize, name: String, path: String },
+    NotADirectory { command: usize, name: String, path: String },
+}
+
+impl fmt::Display for NavigationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NavigationError::UpFromRoot { command } =>
+                write!(f, "Command {command}: can't go up from the root directory"),
+            NavigationError::UnknownDirectory { command, name, path } =>
+                write!(f, "Command {command}: directory {path} has no sub directory '{name}'"),
+            NavigationError::NotADirectory { command, name, path } =>
+                write!(f, "Command {command}: '{name}' in {path} is not a directory"),
+        }
+    }
+}
+
+impl Error for NavigationError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
+}
+
+impl FileSystem {
+    pub fn new() -> Self {
+        let root = Node { name: "/".to_owned(), parent: None, kind: NodeKind::Directory { children: vec![] }, size: 0 };
+        FileSystem { nodes: vec![root] }
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        match &self.nodes[id].kind {
+            NodeKind::Directory { children } => children,
+            _ => &[],
+        }
+    }
+
+    pub fn child(&self, id: NodeId, name: &str) -> Option<NodeId> {
+        self.children(id).iter().copied().find(|&x| self.nodes[x].name == name)
+    }
+
+    pub fn add_directory(&mut self, parent: NodeId, name: &str) -> NodeId {
+        if let Some(existing) = self.child(parent, name) {
+            return existing;
+        }
+        self.push(parent, Node { name: name.to_owned(), parent: Some(parent), kind: NodeKind::Directory { children: vec![] }, size: 0 })
+    }
+
+    pub fn add_file(&mut self, parent: NodeId, name: &str, size: u64) -> NodeId {
+        if let Some(existing) = self.child(parent, name) {
+            return existing;
+        }
+        let id = self.push(parent, Node { name: name.to_owned(), parent: Some(parent), kind: NodeKind::File, size });
+
+        let mut current = Some(parent);
+        while let Some(dir) = current {
+            self.nodes[dir].size += size;
+            current = self.nodes[dir].parent;
+        }
+        id
+    }
+
+    pub fn add_symlink(&mut self, parent: NodeId, name: &str, target: &str) -> NodeId {
+        if let Some(existing) = self.child(parent, name) {
+            return existing;
+        }
+        self.push(parent, Node { name: name.to_owned(), parent: Some(parent), kind: NodeKind::Symlink { target: target.to_owned() }, size: 0 })
+    }
+
+    fn push(&mut self, parent: NodeId, node: Node) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(node);
+        if let NodeKind::Directory { children } = &mut self.nodes[parent].kind {
+            children.push(id);
+        }
+        id
+    }
+
+    pub fn is_directory(&self, id: NodeId) -> bool {
+        matches!(self.nodes[id].kind, NodeKind::Directory { .. })
+    }
+
+    // Follows the path from the given directory, every step but the last has to be a directory.
+    pub fn walk(&self, from: NodeId, path: &[PathComponent], command: usize) -> Result<NodeId, NavigationError> {
+        let mut current = from;
+        for component in path {
+            if !self.is_directory(current) {
+                return Err(self.not_a_directory(current, command));
+            }
+            current = match component {
+                PathComponent::Root => ROOT,
+                PathComponent::Up => self.nodes[current].parent.ok_or(NavigationError::UpFromRoot { command })?,
+                PathComponent::Name(name) => self.child(current, name).ok_or_else(|| NavigationError::UnknownDirectory {
+                    command,
+                    name: name.clone(),
+                    path: self.path_of(current),
+                })?,
+            };
+        }
+        Ok(current)
+    }
+
+    pub fn change_directory(&self, from: NodeId, path: &[PathComponent], command: usize) -> Result<NodeId, NavigationError> {
+        let target = self.walk(from, path, command)?;
+        if !self.is_directory(target) {
+            return Err(self.not_a_directory(target, command));
+        }
+        Ok(target)
+    }
+
+    fn not_a_directory(&self, id: NodeId, command: usize) -> NavigationError {
+        let node = &self.nodes[id];
+        NavigationError::NotADirectory {
+            command,
+            name: node.name.clone(),
+            path: self.path_of(node.parent.unwrap_or(ROOT)),
+        }
+    }
+
+    pub fn root_size(&self) -> u64 {
+        self.nodes[ROOT].size
+    }
+
+    pub fn lookup(&self, path: &str) -> Option<Entry> {
+        self.walk(ROOT, &parse_path(path), 0).ok().map(|id| self.entry(id))
+    }
+
+    pub fn size(&self, id: NodeId) -> u64 {
+        self.nodes[id].size
+    }
+
+    pub fn entry(&self, id: NodeId) -> Entry {
+        let link_target = match &self.nodes[id].kind {
+            NodeKind::Symlink { target } => Some(target.clone()),
+            _ => None,
+        };
+        Entry { path: self.path_of(id), is_directory: self.is_directory(id), link_target, size: self.nodes[id].size }
+    }
+
+    // All entries in depth first order, directories before their content.
+    pub fn entries(&self) -> Vec<Entry> {
+        let mut entries = vec![];
+        let mut stack = vec![ROOT];
+        while let Some(id) = stack.pop() {
+            entries.push(self.entry(id));
+            let mut children = self.children(id).to_vec();
+            children.sort_by_key(|&x| !self.is_directory(x));
+            stack.extend(children.into_iter().rev());
+        }
+        entries
+    }
+
+    pub fn directory_sizes(&self) -> Vec<u64> {
+        self.nodes.iter()
+            .filter(|x| matches!(x.kind, NodeKind::Directory { .. }))
+            .map(|x| x.size)
+            .collect()
+    }
+
+    pub fn find(&self, predicate: impl Fn(&Entry) -> bool) -> Vec<Entry> {
+        self.entries().into_iter().filter(predicate).collect()
+    }
+
+    // Directories sorted from the largest one, like `du | sort -rn`.
+    pub fn du(&self) -> Vec<Entry> {
+        let mut directories = self.find(|x| x.is_directory);
+        directories.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        directories
+    }
+
+    // Same notation as the puzzle description, entries are sorted by name.
+    pub fn tree(&self) -> String {
+        let mut result = String::new();
+        self.print_node(ROOT, 0, &mut result);
+        result
+    }
+
+    fn print_node(&self, id: NodeId, depth: usize, result: &mut String) {
+        let node = &self.nodes[id];
+        let indent = "  ".repeat(depth);
+        match &node.kind {
+            NodeKind::Symlink { target } => result.push_str(&format!("{indent}- {} (symlink -> {target})\n", node.name)),
+            NodeKind::File => result.push_str(&format!("{indent}- {} (file, size={})\n", node.name, node.size)),
+            NodeKind::Directory { .. } => {
+                result.push_str(&format!("{indent}- {} (dir)\n", node.name));
+                let mut children = self.children(id).to_vec();
+                children.sort_by(|&a, &b| self.nodes[a].name.cmp(&self.nodes[b].name));
+                for child in children {
+                    self.print_node(child, depth + 1, result);
+                }
+            },
+        }
+    }
+
+    pub fn path_of(&self, id: NodeId) -> String {
+        let mut names = vec![];
+        let mut current = id;
+        while let Some(parent) = self.nodes[current].parent {
+            names.push(self.nodes[current].name.as_str());
+            current = parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+}
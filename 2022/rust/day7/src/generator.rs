@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+
+use crate::filesystem::{FileSystem, NodeId, NodeKind, ROOT};
+
+// Terminal session listing every directory once, going down with `cd <name>` and back up with `cd ..`.
+pub fn transcript(file_system: &FileSystem) -> Vec<String> {
+    let mut lines = vec!["$ cd /".to_owned()];
+    list(file_system, ROOT, &mut lines);
+    lines
+}
+
+fn list(file_system: &FileSystem, id: NodeId, lines: &mut Vec<String>) {
+    lines.push("$ ls".to_owned());
+    for &child in file_system.children(id) {
+        let node = file_system.node(child);
+        lines.push(match &node.kind {
+            NodeKind::Directory { .. } => format!("dir {}", node.name),
+            NodeKind::File => format!("{} {}", node.size, node.name),
+            NodeKind::Symlink { target } => format!("lrwxrwxrwx 1 user user {} Jan  1 00:00 {} -> {target}", target.len(), node.name),
+        });
+    }
+
+    for &child in file_system.children(id) {
+        if file_system.is_directory(child) {
+            lines.push(format!("$ cd {}", file_system.node(child).name));
+            list(file_system, child, lines);
+            lines.push("$ cd ..".to_owned());
+        }
+    }
+}
+
+// Reads the notation used in the puzzle description and printed by `tree`, two spaces per level:
+//   - / (dir)
+//     - a (dir)
+//       - i (file, size=584)
+//     - b (symlink -> a)
+pub fn from_tree(text: &str) -> Result<FileSystem> {
+    let mut file_system = FileSystem::new();
+    // Directories containing the next line, one per level.
+    let mut parents: Vec<NodeId> = vec![];
+    for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let invalid = |reason: &str| anyhow!("Line {}: {reason} in '{line}'", i + 1);
+        let content = line.trim_start();
+        let indent = line.len() - content.len();
+        let (name, description) = content.strip_prefix("- ")
+            .and_then(|x| x.strip_suffix(')'))
+            .and_then(|x| x.split_once(" ("))
+            .ok_or_else(|| invalid("expected '- <name> (<description>)'"))?;
+
+        if !indent.is_multiple_of(2) || indent / 2 > parents.len() {
+            return Err(invalid("wrong indentation"));
+        }
+        let depth = indent / 2;
+        if depth == 0 {
+            if name != "/" || description != "dir" || !parents.is_empty() {
+                return Err(invalid("the only top level entry has to be '- / (dir)'"));
+            }
+            parents.push(ROOT);
+            continue;
+        }
+
+        parents.truncate(depth);
+        let parent = parents[depth - 1];
+        if description == "dir" {
+            parents.push(file_system.add_directory(parent, name));
+        } else if let Some(size) = description.strip_prefix("file, size=") {
+            let size = size.parse().map_err(|_| invalid("invalid file size"))?;
+            file_system.add_file(parent, name, size);
+        } else if let Some(target) = description.strip_prefix("symlink -> ") {
+            file_system.add_symlink(parent, name, target);
+        } else {
+            return Err(invalid("expected dir, file or symlink"));
+        }
+    }
+
+    if parents.is_empty() {
+        return Err(anyhow!("Tree has no root directory"));
+    }
+    Ok(file_system)
+}
+
+// Symbolic links are kept as links and never followed, entries are added in name order.
+pub fn from_directory(path: &Path) -> Result<FileSystem> {
+    let mut file_system = FileSystem::new();
+    scan(&mut file_system, ROOT, path)?;
+    Ok(file_system)
+}
+
+fn scan(file_system: &mut FileSystem, id: NodeId, path: &Path) -> Result<()> {
+    let mut entries = fs::read_dir(path)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|x| x.file_name());
+    for entry in entries {
+        let name = entry.file_name()
+            .into_string()
+            .map_err(|x| anyhow!("File name {x:?} in {} is not valid UTF-8", path.display()))?;
+        if name.contains('\n') || name.trim() != name {
+            return Err(anyhow!("File name {name:?} in {} can't be written in a terminal session", path.display()));
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            let target = fs::read_link(entry.path())?;
+            file_system.add_symlink(id, &name, &target.to_string_lossy());
+        } else if file_type.is_dir() {
+            let child = file_system.add_directory(id, &name);
+            scan(file_system, child, &entry.path())?;
+        } else {
+            file_system.add_file(id, &name, entry.metadata()?.len());
+        }
+    }
+    Ok(())
+}
@@ -1,82 +1,210 @@
+mod filesystem;
+mod generator;
+mod planner;
+
+use std::env;
+use std::fs;
 use std::io::{self, BufRead};
-use std::rc::{Rc, Weak};
-use std::cell::RefCell;
-use anyhow::{Result, anyhow, Ok};
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use filesystem::{FileSystem, NavigationError, PathComponent};
+use planner::Disk;
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(|x| x.as_str()) {
+        Some("generate") => generate(&args[1..]),
+        Some("verify") => verify(&args[1..]),
+        command => read_file_system().and_then(|file_system| match command {
+            Some("part_one") => part_one(&file_system),
+            Some("tree") => tree(&file_system),
+            Some("du") => du(&file_system),
+            Some("stat") => stat(&file_system, &args[1..]),
+            Some("find") => find(&file_system, &args[1..]),
+            Some("plan") => plan(&file_system, &args[1..]),
+            Some("part_two") => part_two(&file_system, &args[1..]),
+            _ => part_two(&file_system, &args),
+        }),
+    };
+
+    if let Err(err) = result {
+        println!("Error: {err:?}");
+    }
+}
+
+fn read_file_system() -> Result<FileSystem> {
     let stdin = io::stdin();
-    let lines = stdin.lock().lines();
-    let commands = parse_input(lines).unwrap();
-    let root = build_file_tree(commands);
+    let commands = parse_input(stdin.lock().lines())?;
+    Ok(build_file_tree(commands)?)
+}
 
-    part_two(root);
+// File system described by `--tree <file>` in the puzzle's tree notation, scanned from a
+// real directory with `--dir <path>`, or built from a terminal session on the standard input.
+fn source(args: &[String]) -> Result<FileSystem> {
+    if let Some(path) = option_value(args, "--tree") {
+        generator::from_tree(&fs::read_to_string(path)?)
+    } else if let Some(path) = option_value(args, "--dir") {
+        generator::from_directory(Path::new(path))
+    } else {
+        read_file_system()
+    }
 }
 
-fn part_one(root: Rc<RefCell<Directory>>) {
-    let mut total_size: u32 = 0;
-    calc_size(&root.borrow(), &mut total_size);
+fn generate(args: &[String]) -> Result<()> {
+    for line in generator::transcript(&source(args)?) {
+        println!("{line}");
+    }
+    Ok(())
+}
 
-    println!("Total size is {total_size}");
+// Checks that the generated session is read back into the same entries with the same sizes.
+fn verify(args: &[String]) -> Result<()> {
+    let file_system = source(args)?;
+    let transcript = generator::transcript(&file_system);
+    let rebuilt = build_file_tree(parse_input(transcript.into_iter().map(Ok))?)?;
 
-    fn calc_size(dir: &Directory, total_size: &mut u32) -> u32 {
-        let mut dir_size: u32 = dir.files.iter().map(|f| f.size).sum();
-        for sub_dir in dir.sub_directories.iter().map(|x| x.borrow()) {
-            dir_size += calc_size(&sub_dir, total_size);
-        }
-        
-        if dir_size <= 100000 {
-            *total_size += dir_size;
-        }
-        dir_size
+    let sorted_entries = |file_system: &FileSystem| {
+        let mut entries = file_system.entries();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries
+    };
+    let expected = sorted_entries(&file_system);
+    let actual = sorted_entries(&rebuilt);
+    if let Some((a, b)) = expected.iter().zip(actual.iter()).find(|(a, b)| a != b) {
+        return Err(anyhow!("Entry {a:?} was read back as {b:?}"));
+    }
+    if expected.len() != actual.len() {
+        return Err(anyhow!("{} entries were read back as {}", expected.len(), actual.len()));
     }
+
+    println!("Round trip kept all {} entries, total size {}", actual.len(), rebuilt.root_size());
+    Ok(())
+}
+
+fn part_one(file_system: &FileSystem) -> Result<()> {
+    let total_size: u64 = file_system.directory_sizes()
+        .into_iter()
+        .filter(|&x| x <= 100000)
+        .sum();
+
+    println!("Total size is {total_size}");
+    Ok(())
 }
 
-fn part_two(root: Rc<RefCell<Directory>>) {
-    let mut all_sizes = vec![];
-    let used_space = calculate_and_fill_sizes(&root.borrow(), &mut all_sizes);
-    let unused_space = 70_000_000 - used_space;
-    let space_to_free_up = 30_000_000 - unused_space;
-    let size_to_delete = all_sizes.iter()
-        .filter(|&x| x > &space_to_free_up)
-        .min().unwrap();
+fn disk(args: &[String]) -> Result<Disk> {
+    Ok(Disk {
+        total: option_value(args, "--disk").map(|x| x.parse()).transpose()?.unwrap_or(70_000_000),
+        required: option_value(args, "--required").map(|x| x.parse()).transpose()?.unwrap_or(30_000_000),
+    })
+}
+
+fn part_two(file_system: &FileSystem, args: &[String]) -> Result<()> {
+    let space_to_free_up = disk(args)?.space_to_free(file_system.root_size())?;
+    if space_to_free_up == 0 {
+        println!("There is already enough unused space");
+        return Ok(());
+    }
+
+    let size_to_delete = planner::smallest_directory(file_system, space_to_free_up)
+        .ok_or_else(|| anyhow!("No directory is big enough"))?;
 
     println!("Directory to delete has size: {size_to_delete}");
+    Ok(())
+}
 
-    fn calculate_and_fill_sizes(dir: &Directory, all_sizes: &mut Vec<u32>) -> u32 {
-        let mut dir_size: u32 = dir.files.iter().map(|f| f.size).sum();
-        for sub_dir in dir.sub_directories.iter().map(|x| x.borrow()) {
-            dir_size += calculate_and_fill_sizes(&sub_dir, all_sizes);
-        }
+fn plan(file_system: &FileSystem, args: &[String]) -> Result<()> {
+    let space_to_free_up = disk(args)?.space_to_free(file_system.root_size())?;
+    println!("Space to free up: {space_to_free_up}");
+    if space_to_free_up == 0 {
+        return Ok(());
+    }
+
+    if let Some(entry) = file_system.du().into_iter().rev().find(|x| x.size >= space_to_free_up) {
+        println!("Single directory: {}\t{}", entry.size, entry.path);
+    }
+    let directories = planner::plan(file_system, space_to_free_up)?
+        .ok_or_else(|| anyhow!("Deleting everything doesn't free up enough space"))?;
+    let total: u64 = directories.iter().map(|x| x.size).sum();
+    println!("Best set of directories, total {total}:");
+    for entry in directories {
+        println!("{}\t{}", entry.size, entry.path);
+    }
+    Ok(())
+}
+
+fn tree(file_system: &FileSystem) -> Result<()> {
+    print!("{}", file_system.tree());
+    Ok(())
+}
 
-        all_sizes.push(dir_size);
-        dir_size
+fn du(file_system: &FileSystem) -> Result<()> {
+    for entry in file_system.du() {
+        println!("{}\t{}", entry.size, entry.path);
     }
+    Ok(())
+}
+
+fn stat(file_system: &FileSystem, args: &[String]) -> Result<()> {
+    let path = args.first().ok_or_else(|| anyhow!("Usage: day7 stat <absolute path>"))?;
+    let entry = file_system.lookup(path).ok_or_else(|| anyhow!("No such file or directory: {path}"))?;
+    match (&entry.link_target, entry.is_directory) {
+        (Some(target), _) => println!("{} (symlink -> {target})", entry.path),
+        (None, true) => println!("{} (dir, size={})", entry.path, entry.size),
+        (None, false) => println!("{} (file, size={})", entry.path, entry.size),
+    }
+    Ok(())
+}
+
+fn find(file_system: &FileSystem, args: &[String]) -> Result<()> {
+    let name = option_value(args, "--name");
+    let kind = option_value(args, "--type");
+    let min_size: Option<u64> = option_value(args, "--min-size").map(|x| x.parse()).transpose()?;
+    let max_size: Option<u64> = option_value(args, "--max-size").map(|x| x.parse()).transpose()?;
+
+    let entries = file_system.find(|entry| {
+        let entry_name = entry.path.rsplit('/').next().unwrap_or("");
+        name.is_none_or(|x| entry_name.contains(x))
+            && kind.is_none_or(|x| match x {
+                "d" => entry.is_directory,
+                "l" => entry.link_target.is_some(),
+                _ => !entry.is_directory && entry.link_target.is_none(),
+            })
+            && min_size.is_none_or(|x| entry.size >= x)
+            && max_size.is_none_or(|x| entry.size <= x)
+    });
+
+    for entry in entries {
+        println!("{}\t{}", entry.size, entry.path);
+    }
+    Ok(())
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|x| x == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|x| x.as_str())
 }
 
-fn parse_input(lines: io::Lines<io::StdinLock<'_>>) -> Result<Vec<CommandWithOutput>> {
-    let mut lines_iter = lines.into_iter();
+fn parse_input(lines: impl Iterator<Item = io::Result<String>>) -> Result<Vec<CommandWithOutput>> {
     let mut all_commands: Vec<CommandWithOutput> = Vec::new();
     let mut current_command: Option<CommandWithOutput> = None;
-    while let Some(line) = lines_iter.next() {
+    for (i, line) in lines.enumerate() {
         let line = line?;
-        if line.starts_with('$') {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(command) = line.strip_prefix('$') {
             if let Some(command) = current_command {
                 all_commands.push(command);
             }
-            current_command = Some(parse_command(&line)?);
+            current_command = Some(parse_command(command.trim()).map_err(|err| anyhow!("Line {}: {err}", i + 1))?);
         }
         else if let Some(CommandWithOutput::Ls(ls_command)) = &mut current_command {
-            match line {
-                l if l.starts_with("dir") => ls_command.directories.push(l[4..].to_owned()),
-                l if l.chars().next().unwrap().is_ascii_digit() => {
-                    let (size_str, file_name) = l.split_once(' ').unwrap();
-                    ls_command.files.push(File {
-                        name: file_name.to_owned(),
-                        size: size_str.parse().unwrap(),
-                    })
-                },
-                _ => panic!()
-            }
+            ls_command.add_output(&line).map_err(|err| anyhow!("Line {}: {err}", i + 1))?;
+        }
+        else {
+            return Err(anyhow!("Line {}: output '{line}' doesn't follow an ls command", i + 1));
         }
     }
 
@@ -85,106 +213,202 @@ fn parse_input(lines: io::Lines<io::StdinLock<'_>>) -> Result<Vec<CommandWithOut
     }
 
     return Ok(all_commands);
-    
+
     fn parse_command(line: &str) -> Result<CommandWithOutput> {
-        let line = &line[2..];
-        let command = match line {
-            "cd /" => CommandWithOutput::Cd(CdCommand::Root),
-            "cd .." => CommandWithOutput::Cd(CdCommand::Up),
-            cmd if cmd.starts_with("cd ") => {
-                let dir_name = &cmd[3..];
-                CommandWithOutput::Cd(CdCommand::ToDirectory(dir_name.to_owned()))
+        match line.split_whitespace().next() {
+            // The rest of the line is the path, so directory names may contain spaces.
+            Some("cd") => {
+                let path = line[2..].trim();
+                if path.is_empty() {
+                    return Err(anyhow!("cd needs a directory"));
+                }
+                Ok(CommandWithOutput::Cd(filesystem::parse_path(path)))
+            },
+            // Options like -l or -a only change how entries are printed, which add_output handles.
+            // What follows them is the path, the same way as for cd.
+            Some("ls") => {
+                let mut path = line[2..].trim_start();
+                while path.starts_with('-') {
+                    path = path.split_once(char::is_whitespace).map_or("", |(_, rest)| rest.trim_start());
+                }
+                let path = path.trim_end();
+                Ok(LsCommand::of(if path.is_empty() { vec![] } else { filesystem::parse_path(path) }))
             },
-            "ls" => CommandWithOutput::Ls(LsCommand::empty()),
-            _ => return Err(anyhow!("Not supported command")),
-        };
-        Ok(command)
+            _ => Err(anyhow!("Not supported command '{line}'")),
+        }
     }
 }
 
-fn build_file_tree(commands: Vec<CommandWithOutput>) -> Rc<RefCell<Directory>> {
-    let root = Rc::new(RefCell::new(Directory::parent("/".to_owned())));
-    let mut current_directory = root.clone();
-    for command in commands {
+fn build_file_tree(commands: Vec<CommandWithOutput>) -> Result<FileSystem, NavigationError> {
+    let mut file_system = FileSystem::new();
+    let mut current_directory = filesystem::ROOT;
+    for (i, command) in commands.into_iter().enumerate() {
+        let command_number = i + 1;
         match command {
-            CommandWithOutput::Cd(CdCommand::Root) => current_directory = root.clone(),
-            CommandWithOutput::Cd(CdCommand::Up) => {
-                let temp = current_directory;
-                current_directory = temp.borrow() 
-                    .parent.as_ref().unwrap()
-                    .upgrade().unwrap();
-            },
-            CommandWithOutput::Cd(CdCommand::ToDirectory(dir_name)) => {
-                let temp = current_directory;
-                current_directory = temp.borrow().sub_directories
-                    .iter()
-                    .find(|&dir| dir.borrow().name.eq(&dir_name))
-                    .unwrap()
-                    .clone();
+            CommandWithOutput::Cd(path) => {
+                current_directory = file_system.change_directory(current_directory, &path, command_number)?;
             },
-            CommandWithOutput::Ls(LsCommand { directories, files }) => {
-                let mut directory = current_directory.borrow_mut();
-                directory.files = files;
-                directory.sub_directories = directories.into_iter()
-                    .map(|name| Rc::new(RefCell::new(Directory::new(name, Rc::downgrade(&current_directory)))))
-                    .collect();
+            // Listing the same directory again adds the new entries to the ones already known.
+            CommandWithOutput::Ls(LsCommand { path, directories, files, symlinks }) => {
+                let directory = file_system.change_directory(current_directory, &path, command_number)?;
+                for name in directories {
+                    file_system.add_directory(directory, &name);
+                }
+                for file in files {
+                    file_system.add_file(directory, &file.name, file.size);
+                }
+                for symlink in symlinks {
+                    file_system.add_symlink(directory, &symlink.name, &symlink.target);
+                }
             }
         }
     }
 
-    root
+    Ok(file_system)
 }
 
 enum CommandWithOutput {
-    Cd(CdCommand),
+    Cd(Vec<PathComponent>),
     Ls(LsCommand),
 }
 
-enum CdCommand {
-    Root,
-    Up,
-    ToDirectory(String),
-}
-
+// Path is relative to the current directory, empty for a plain `ls`.
 struct LsCommand {
+    path: Vec<PathComponent>,
     directories: Vec<String>,
     files: Vec<File>,
+    symlinks: Vec<Symlink>,
 }
 
 impl LsCommand {
-    fn empty() -> Self {
-        LsCommand { directories: Vec::new(), files: Vec::new() }
+    fn of(path: Vec<PathComponent>) -> CommandWithOutput {
+        CommandWithOutput::Ls(LsCommand { path, directories: Vec::new(), files: Vec::new(), symlinks: Vec::new() })
     }
+
+    fn add_output(&mut self, line: &str) -> Result<()> {
+        if let Some(name) = line.strip_prefix("dir ") {
+            self.directories.push(name.to_owned());
+        }
+        else if line.starts_with(|c: char| c.is_ascii_digit()) {
+            let (size, name) = line.split_once(' ').ok_or_else(|| anyhow!("File '{line}' has no name"))?;
+            self.files.push(File { name: name.to_owned(), size: parse_size(size)? });
+        }
+        // Block count printed by ls -l before the entries.
+        else if line.starts_with("total ") {
+        }
+        else if let Some((permissions, size, name)) = long_listing(line) {
+            match permissions.chars().next() {
+                Some('d') => self.directories.push(name.to_owned()),
+                Some('-') => self.files.push(File { name: name.to_owned(), size: parse_size(size)? }),
+                Some('l') => {
+                    let (name, target) = name.split_once(" -> ").ok_or_else(|| anyhow!("Symbolic link '{name}' has no target"))?;
+                    self.symlinks.push(Symlink { name: name.to_owned(), target: target.to_owned() });
+                },
+                _ => return Err(anyhow!("Not supported file type in '{permissions}'")),
+            }
+        }
+        else {
+            return Err(anyhow!("Unexpected ls output '{line}'"));
+        }
+        Ok(())
+    }
+}
+
+fn parse_size(size: &str) -> Result<u64> {
+    size.parse().map_err(|_| anyhow!("Invalid file size '{size}'"))
+}
+
+// `ls -l` lines: permissions, links, owner, group, size, month, day, time or year and the name.
+fn long_listing(line: &str) -> Option<(&str, &str, &str)> {
+    let mut rest = line;
+    let mut fields = vec![];
+    for _ in 0..8 {
+        let (field, tail) = rest.trim_start().split_once(char::is_whitespace)?;
+        fields.push(field);
+        rest = tail;
+    }
+    let permissions = fields[0];
+    let is_permissions = permissions.chars().count() >= 10
+        && permissions.chars().skip(1).take(9).all(|c| "rwxsStTl-".contains(c));
+    let name = rest.trim_start();
+    if !is_permissions || name.is_empty() {
+        return None;
+    }
+    Some((permissions, fields[4], name))
 }
 
 struct File {
     name: String,
-    size: u32,
+    size: u64,
 }
 
-struct Directory {
+struct Symlink {
     name: String,
-    sub_directories: Vec<Rc<RefCell<Directory>>>,
-    files: Vec<File>,
-    parent: Option<Weak<RefCell<Directory>>>,
+    target: String,
 }
 
-impl Directory {
-    fn parent(name: String) -> Self {
-        Directory { 
-            name,
-            sub_directories: vec![],
-            files: vec![],
-            parent: None,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filesystem::{NodeId, ROOT};
+
+    // Xorshift, enough to vary the trees without pulling in a crate for it.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+
+        fn name(&mut self, prefix: &str, index: usize) -> String {
+            let words = ["a", "b c", "with  two spaces", "x.txt", "d e f.log"];
+            format!("{prefix}{index} {}", words[self.below(words.len() as u64) as usize])
+        }
+    }
+
+    fn random_tree(random: &mut Random, file_system: &mut FileSystem, parent: NodeId, depth: usize) {
+        for i in 0..random.below(6) as usize {
+            match random.below(if depth < 4 { 4 } else { 3 }) {
+                0 | 1 => {
+                    let name = random.name("file", i);
+                    file_system.add_file(parent, &name, random.below(1_000_000));
+                },
+                2 => {
+                    let name = random.name("link", i);
+                    let target = random.name("../target ", i);
+                    file_system.add_symlink(parent, &name, &target);
+                },
+                _ => {
+                    let name = random.name("dir", i);
+                    let child = file_system.add_directory(parent, &name);
+                    random_tree(random, file_system, child, depth + 1);
+                },
+            }
         }
     }
 
-    fn new(name: String, parent: Weak<RefCell<Directory>>) -> Self {
-        Directory { 
-            name,
-            sub_directories: vec![],
-            files: vec![],
-            parent: Some(parent),
+    fn sorted_entries(file_system: &FileSystem) -> Vec<filesystem::Entry> {
+        let mut entries = file_system.entries();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries
+    }
+
+    #[test]
+    fn transcript_round_trip_keeps_random_trees() {
+        let mut random = Random(0x2022_0007);
+        for _ in 0..200 {
+            let mut file_system = FileSystem::new();
+            random_tree(&mut random, &mut file_system, ROOT, 0);
+
+            let transcript = generator::transcript(&file_system);
+            let commands = parse_input(transcript.into_iter().map(Ok)).unwrap();
+            let rebuilt = build_file_tree(commands).unwrap();
+
+            assert_eq!(sorted_entries(&rebuilt), sorted_entries(&file_system));
+            assert_eq!(rebuilt.root_size(), file_system.root_size());
         }
     }
 }
@@ -0,0 +1,242 @@
+use std::collections::BTreeSet;
+use anyhow::{Result, anyhow};
+
+use crate::filesystem::{Entry, FileSystem, NodeId, ROOT};
+
+pub struct Disk {
+    pub total: u64,
+    pub required: u64,
+}
+
+impl Disk {
+    pub fn space_to_free(&self, used_space: u64) -> Result<u64> {
+        let unused_space = self.total.checked_sub(used_space)
+            .ok_or_else(|| anyhow!("Used space {used_space} is more than the disk size {}", self.total))?;
+        Ok(self.required.saturating_sub(unused_space))
+    }
+}
+
+pub fn smallest_directory(file_system: &FileSystem, space_to_free: u64) -> Option<u64> {
+    file_system.directory_sizes()
+        .into_iter()
+        .filter(|&x| x >= space_to_free)
+        .min()
+}
+
+// Bit sets over every total below the needed space are used while they fit in this many
+// bytes, bigger sizes keep only the totals that can actually be reached, as long as those
+// fit in about the same memory.
+const DENSE_LIMIT_BYTES: usize = 256 << 20;
+const SPARSE_LIMIT_TOTALS: usize = DENSE_LIMIT_BYTES / 32;
+
+enum Totals {
+    Dense(Bits),
+    Sparse(BTreeSet<u64>, u64),
+}
+
+impl Totals {
+    fn contains(&self, value: u64) -> bool {
+        match self {
+            Totals::Dense(bits) => bits.contains(value as usize),
+            Totals::Sparse(set, _) => set.contains(&value),
+        }
+    }
+
+    fn insert(&mut self, value: u64) {
+        match self {
+            Totals::Dense(bits) => bits.insert(value as usize),
+            Totals::Sparse(set, _) => { set.insert(value); },
+        }
+    }
+
+    fn union(&mut self, other: &Totals) {
+        match (self, other) {
+            (Totals::Dense(bits), Totals::Dense(other)) => bits.union(other),
+            (Totals::Sparse(set, _), Totals::Sparse(other, _)) => set.extend(other.iter().copied()),
+            _ => unreachable!(),
+        }
+    }
+
+    // Adds every value of other increased by shift, values past the needed space are dropped.
+    fn union_shifted(&mut self, other: &Totals, shift: u64) {
+        match (self, other) {
+            (Totals::Dense(bits), Totals::Dense(other)) => bits.union_shifted(other, shift as usize),
+            (Totals::Sparse(set, len), Totals::Sparse(other, _)) => {
+                set.extend(other.iter().map(|x| x + shift).take_while(|x| x < len));
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn first_from(&self, start: u64) -> Option<u64> {
+        match self {
+            Totals::Dense(bits) => bits.first_from(start as usize).map(|x| x as u64),
+            Totals::Sparse(set, _) => set.range(start..).next().copied(),
+        }
+    }
+
+    // Number of totals kept in memory, bit sets are sized up front so they count as none.
+    fn stored(&self) -> usize {
+        match self {
+            Totals::Dense(_) => 0,
+            Totals::Sparse(set, _) => set.len(),
+        }
+    }
+}
+
+struct Bits {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bits {
+    fn new(len: usize) -> Self {
+        Bits { words: vec![0; len.div_ceil(64)], len }
+    }
+
+    fn contains(&self, value: usize) -> bool {
+        value < self.len && (self.words[value / 64] >> (value % 64)) & 1 == 1
+    }
+
+    fn insert(&mut self, value: usize) {
+        self.words[value / 64] |= 1 << (value % 64);
+    }
+
+    fn union(&mut self, other: &Bits) {
+        for (word, other) in self.words.iter_mut().zip(other.words.iter()) {
+            *word |= other;
+        }
+    }
+
+    // Adds every value of other increased by shift, values that don't fit are dropped.
+    fn union_shifted(&mut self, other: &Bits, shift: usize) {
+        let (offset, bits) = (shift / 64, shift % 64);
+        for (i, &word) in other.words.iter().enumerate() {
+            let Some(target) = self.words.get_mut(i + offset) else { break };
+            *target |= word << bits;
+            if bits > 0 {
+                if let Some(target) = self.words.get_mut(i + offset + 1) {
+                    *target |= word >> (64 - bits);
+                }
+            }
+        }
+        if !self.len.is_multiple_of(64) {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << (self.len % 64)) - 1;
+            }
+        }
+    }
+
+    fn first_from(&self, start: usize) -> Option<usize> {
+        (start..self.len).find(|&x| self.contains(x))
+    }
+}
+
+// Smallest total of at least the needed space reachable at a position. It was reached from
+// the position in `from`, either by skipping that directory or by deleting it on top of the
+// smaller total in `taken_after`.
+#[derive(Clone, Copy)]
+struct Enough {
+    total: u64,
+    from: usize,
+    taken_after: Option<u64>,
+}
+
+// Set of directories, none inside another, freeing at least the given space with the
+// smallest total size. Directories are visited in pre-order: skipping one moves on to the
+// next, deleting one jumps past its whole subtree. Totals below the needed space are kept
+// per position and every total above it collapses to the smallest one.
+pub fn plan(file_system: &FileSystem, space_to_free: u64) -> Result<Option<Vec<Entry>>> {
+    if space_to_free == 0 {
+        return Ok(Some(vec![]));
+    }
+    if space_to_free > file_system.root_size() {
+        return Ok(None);
+    }
+
+    let mut order = vec![];
+    let mut ends = vec![];
+    pre_order(file_system, ROOT, &mut order, &mut ends);
+
+    let count = order.len();
+    let dense = (space_to_free / 8).saturating_mul(count as u64 + 1) <= DENSE_LIMIT_BYTES as u64;
+    let new_totals = || match dense {
+        true => Totals::Dense(Bits::new(space_to_free as usize)),
+        false => Totals::Sparse(BTreeSet::new(), space_to_free),
+    };
+    let mut below = (0..=count).map(|_| new_totals()).collect::<Vec<_>>();
+    let mut enough: Vec<Option<Enough>> = vec![None; count + 1];
+    below[0].insert(0);
+    let mut stored = 1;
+
+    for position in 0..count {
+        let (before, after) = below.split_at_mut(position + 1);
+        let current = &before[position];
+        let size = file_system.size(order[position]);
+        let end = ends[position];
+
+        let before_union = after[0].stored() + after[end - position - 1].stored();
+        after[0].union(current);
+        if size < space_to_free {
+            after[end - position - 1].union_shifted(current, size);
+        }
+        stored += after[0].stored() + after[end - position - 1].stored() - before_union;
+        if stored > SPARSE_LIMIT_TOTALS {
+            return Err(anyhow!("Too many combinations of directory sizes to free up {space_to_free}"));
+        }
+
+        let skipped = enough[position].map(|x| Enough { total: x.total, from: position, taken_after: None });
+        let taken = current.first_from(space_to_free.saturating_sub(size))
+            .map(|x| Enough { total: x + size, from: position, taken_after: Some(x) });
+        keep_smallest(&mut enough[position + 1], skipped);
+        keep_smallest(&mut enough[end], taken);
+    }
+
+    let Some(mut step) = enough[count] else { return Ok(None) };
+    while step.taken_after.is_none() {
+        step = enough[step.from].unwrap();
+    }
+    let mut chosen = vec![order[step.from]];
+    let mut position = step.from;
+    let mut total = step.taken_after.unwrap();
+
+    // The rest of the set adds up to a total below the needed space, walked back the same way.
+    while position > 0 {
+        if below[position - 1].contains(total) {
+            position -= 1;
+            continue;
+        }
+        let (previous, size) = (0..position)
+            .filter(|&i| ends[i] == position)
+            .map(|i| (i, file_system.size(order[i])))
+            .find(|&(i, size)| size <= total && below[i].contains(total - size))
+            .unwrap();
+        chosen.push(order[previous]);
+        total -= size;
+        position = previous;
+    }
+
+    chosen.reverse();
+    Ok(Some(chosen.into_iter().map(|id| file_system.entry(id)).collect()))
+}
+
+fn keep_smallest(slot: &mut Option<Enough>, candidate: Option<Enough>) {
+    if let Some(candidate) = candidate {
+        if slot.is_none_or(|x| candidate.total < x.total) {
+            *slot = Some(candidate);
+        }
+    }
+}
+
+// Directories in pre-order, each with the position right after its subtree.
+fn pre_order(file_system: &FileSystem, id: NodeId, order: &mut Vec<NodeId>, ends: &mut Vec<usize>) {
+    let position = order.len();
+    order.push(id);
+    ends.push(0);
+    for &child in file_system.children(id).iter() {
+        if file_system.is_directory(child) {
+            pre_order(file_system, child, order, ends);
+        }
+    }
+    ends[position] = order.len();
+}
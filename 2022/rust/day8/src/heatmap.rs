@@ -0,0 +1,88 @@
+use std::fs;
+use std::io::{BufWriter, Write};
+use anyhow::Result;
+
+use crate::Map;
+use crate::sweep::{self, FROM_BOTTOM, FROM_LEFT, FROM_RIGHT, FROM_TOP};
+
+pub struct Forest {
+    pub heights: Vec<Vec<u32>>,
+    pub visibility: Vec<Vec<u8>>,
+    pub scenic_scores: Vec<Vec<u64>>,
+    max_score: u64,
+}
+
+impl Forest {
+    pub fn analyze(map: &Map) -> Self {
+        let scenic_scores = sweep::scenic_scores(map);
+        let max_score = scenic_scores.iter().flatten().copied().max().unwrap_or(0);
+        Forest {
+            heights: map.0.clone(),
+            visibility: sweep::visibility(map),
+            scenic_scores,
+            max_score,
+        }
+    }
+
+    // Location (row, column) and score of the first tree with the highest scenic score.
+    pub fn best_tree(&self) -> Option<(usize, usize, u64)> {
+        self.scenic_scores.iter()
+            .enumerate()
+            .flat_map(|(i, row)| row.iter().enumerate().map(move |(j, &score)| (i, j, score)))
+            .rev()
+            .max_by_key(|&(_, _, score)| score)
+    }
+
+    pub fn write_csv(&self, path: &str) -> Result<()> {
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        writeln!(out, "row,col,height,visible_from_top,visible_from_bottom,visible_from_left,visible_from_right,scenic_score")?;
+        for (i, row) in self.heights.iter().enumerate() {
+            for (j, height) in row.iter().enumerate() {
+                let visible = self.visibility[i][j];
+                let flag = |direction: u8| u8::from(visible & direction != 0);
+                writeln!(out, "{i},{j},{height},{},{},{},{},{}",
+                    flag(FROM_TOP), flag(FROM_BOTTOM), flag(FROM_LEFT), flag(FROM_RIGHT), self.scenic_scores[i][j])?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn write_ppm(&self, path: &str, scale: usize) -> Result<()> {
+        let rows = self.scenic_scores.len();
+        let cols = self.scenic_scores.first().map_or(0, |x| x.len());
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        writeln!(out, "P6\n{} {}\n255", cols * scale, rows * scale)?;
+        for row in self.scenic_scores.iter() {
+            let pixels = row.iter()
+                .flat_map(|&score| std::iter::repeat_n(self.color(score), scale))
+                .flatten()
+                .collect::<Vec<_>>();
+            for _ in 0..scale {
+                out.write_all(&pixels)?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn print(&self) {
+        let best = self.best_tree();
+        for (i, row) in self.scenic_scores.iter().enumerate() {
+            let mut line = String::new();
+            for (j, &score) in row.iter().enumerate() {
+                let [r, g, b] = self.color(score);
+                let mark = if best.is_some_and(|(x, y, _)| (x, y) == (i, j)) { '*' } else { ' ' };
+                line.push_str(&format!("\x1b[48;2;{r};{g};{b}m{mark}"));
+            }
+            println!("{line}\x1b[0m");
+        }
+    }
+
+    // Black through red and yellow to white, square root spreads the many low scores apart.
+    fn color(&self, score: u64) -> [u8; 3] {
+        let t = (score as f64 / self.max_score.max(1) as f64).sqrt() * 3.0;
+        let channel = |offset: f64| ((t - offset).clamp(0.0, 1.0) * 255.0).round() as u8;
+        [channel(0.0), channel(1.0), channel(2.0)]
+    }
+}
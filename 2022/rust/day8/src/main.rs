@@ -1,3 +1,7 @@
+mod heatmap;
+mod sweep;
+
+use std::env;
 use std::io::{self, BufRead};
 use anyhow::{Result, anyhow, Ok};
 
@@ -5,43 +9,156 @@ fn main() {
     let stdin = io::stdin();
     let lines = stdin.lock().lines();
 
-    let map = Map::parse_from_lines(lines.map(|x|x.unwrap()));
-    part_two(map);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = lines.collect::<Result<Vec<_>, _>>()
+        .map_err(anyhow::Error::from)
+        .and_then(|lines| Map::parse_from_lines(lines.into_iter()))
+        .and_then(|map| match args.first().map(|x| x.as_str()) {
+            Some("part_one") => part_one(map),
+            Some("verify") => verify(map),
+            Some("heatmap") => heatmap(map, &args[1..]),
+            _ => part_two(map),
+        });
+
+    if let Err(err) = result {
+        println!("Error: {err:?}");
+    }
 }
 
-fn part_one(map: Map) {
-    let visible_count = (0..map.0.len())
-        .fold(0, |acc_row, row| acc_row + (0..map.0[0].len())
-              .into_iter()
-              .filter(|col| map.is_visible(row, *col).unwrap())
-              .count());
+fn part_one(map: Map) -> Result<()> {
+    let visible_count = sweep::visibility(&map)
+        .iter()
+        .flatten()
+        .filter(|&&x| x != 0)
+        .count();
 
     println!("Visible count is {visible_count}");
+    Ok(())
+}
+
+fn part_two(map: Map) -> Result<()> {
+    let forest = heatmap::Forest::analyze(&map);
+    let (row, col, scenic_score) = forest.best_tree().ok_or_else(|| anyhow!("Map is empty"))?;
+
+    println!("Scenic score is {scenic_score} at row {}, column {}", row + 1, col + 1);
+    Ok(())
 }
 
-fn part_two(map: Map) {
-    let scenic_score = (0..map.0.len())
-        .flat_map(|i| (0..map.0[0].len()).map(move |j| (i, j)))
-        .map(|(i, j)| map.scenic_score(i, j).unwrap())
-        .max().unwrap();
+fn heatmap(map: Map, args: &[String]) -> Result<()> {
+    let forest = heatmap::Forest::analyze(&map);
+    let csv = option_value(args, "--csv");
+    let ppm = option_value(args, "--ppm");
+    let scale = option_value(args, "--scale").map(|x| x.parse()).transpose()?.unwrap_or(4);
+
+    if let Some(path) = csv {
+        forest.write_csv(path)?;
+        println!("Visibility and scenic scores written to {path}");
+    }
+    if let Some(path) = ppm {
+        forest.write_ppm(path, scale)?;
+        println!("Scenic score heat map written to {path}");
+    }
+    if csv.is_none() && ppm.is_none() {
+        forest.print();
+    }
+
+    if let Some((row, col, scenic_score)) = forest.best_tree() {
+        println!("Best tree is at row {}, column {} with scenic score {scenic_score}", row + 1, col + 1);
+    }
+    Ok(())
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|x| x == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|x| x.as_str())
+}
+
+// Compares the linear sweeps with the straightforward per-tree scans.
+fn verify(map: Map) -> Result<()> {
+    let visibility = sweep::visibility(&map);
+    let scenic_scores = sweep::scenic_scores(&map);
+    let (rows, cols) = map.size();
+
+    for i in 0..rows {
+        for j in 0..cols {
+            let visible = map.is_visible(i, j)?;
+            if visible != (visibility[i][j] != 0) {
+                return Err(anyhow!("Visibility of tree ({i}, {j}) differs: scan gives {visible}, sweep gives {}", visibility[i][j] != 0));
+            }
+
+            let scenic_score = map.scenic_score(i, j)?;
+            if scenic_score != scenic_scores[i][j] {
+                return Err(anyhow!("Scenic score of tree ({i}, {j}) differs: scan gives {scenic_score}, sweep gives {}", scenic_scores[i][j]));
+            }
+        }
+    }
 
-    println!("Scenic score is {scenic_score}");
+    println!("Sweeps match scans for all {} trees", rows * cols);
+    Ok(())
 }
 
-struct Map(Vec<Vec<u8>>);
+struct Map(Vec<Vec<u32>>);
 
 impl Map {
-    fn parse_from_lines(lines: impl Iterator<Item = String>) -> Self {
-        let mut rows = vec![];
-        for line in lines {
-            let mut row = vec![];
-            for c in line.chars() {
-                row.push(c.to_digit(10).unwrap() as u8)
+    // Rows are either digits without separators ("30373") or comma separated heights ("3,10,7").
+    // Empty lines are only allowed at the end of the input.
+    fn parse_from_lines(lines: impl Iterator<Item = String>) -> Result<Self> {
+        let mut rows: Vec<Vec<u32>> = vec![];
+        let mut first_empty_line = None;
+        for (i, line) in lines.enumerate() {
+            let line_number = i + 1;
+            if line.trim().is_empty() {
+                first_empty_line.get_or_insert(line_number);
+                continue;
+            }
+            if let Some(empty_line) = first_empty_line {
+                return Err(anyhow!("Line {empty_line} is empty"));
+            }
+
+            let row = if line.contains(',') {
+                parse_separated_row(&line, line_number)?
+            } else {
+                parse_digits_row(&line, line_number)?
+            };
+
+            if let Some(first_row) = rows.first() {
+                if row.len() != first_row.len() {
+                    return Err(anyhow!("Line {line_number} has {} trees but line 1 has {}", row.len(), first_row.len()));
+                }
             }
             rows.push(row);
         }
 
-        Self(rows)
+        if rows.is_empty() {
+            return Err(anyhow!("Input has no trees"));
+        }
+        return Ok(Self(rows));
+
+        fn parse_digits_row(line: &str, line_number: usize) -> Result<Vec<u32>> {
+            line.chars()
+                .enumerate()
+                .map(|(j, c)| c.to_digit(10)
+                    .ok_or_else(|| anyhow!("Line {line_number}, column {}: '{c}' is not a digit", j + 1)))
+                .collect()
+        }
+
+        fn parse_separated_row(line: &str, line_number: usize) -> Result<Vec<u32>> {
+            let mut row = vec![];
+            let mut column = 1;
+            for field in line.split(',') {
+                let height = field.trim().parse()
+                    .map_err(|_| anyhow!("Line {line_number}, column {column}: '{}' is not a height", field.trim()))?;
+                row.push(height);
+                column += field.chars().count() + 1;
+            }
+            Ok(row)
+        }
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (self.0.len(), self.0.first().map_or(0, |x| x.len()))
     }
 
     fn is_visible(&self, row: usize, col: usize) -> Result<bool> {
@@ -57,30 +174,30 @@ impl Map {
 
         // from top
         let mut visible = self.0[row][..col]
-            .into_iter()
+            .iter()
             .all(|x| x < &current_height);
 
         // from bottom
         visible |= self.0[row][(col + 1)..]
-            .into_iter()
+            .iter()
             .all(|x| x < &current_height);
 
         // from left
         visible |= self.0[..row]
-            .into_iter()
+            .iter()
             .map(|x| x[col])
             .all(|x| x < current_height);
 
         // from bottom
         visible |= self.0[(row + 1)..]
-            .into_iter()
+            .iter()
             .map(|x| x[col])
             .all(|x| x < current_height);
             
         Ok(visible)
     }
 
-    fn scenic_score(&self, row: usize, col: usize) -> Result<u32> {
+    fn scenic_score(&self, row: usize, col: usize) -> Result<u64> {
         if row >= self.0.len() || col >= self.0[0].len() {
             return Err(anyhow!("Index out of range"));
         }
@@ -104,8 +221,8 @@ impl Map {
 
         return Ok(scenic_score);
 
-        fn visible_count(sequence: impl Iterator<Item=u8>, height: u8) -> u32 {
-            let mut count: u32 = 0;
+        fn visible_count(sequence: impl Iterator<Item=u32>, height: u32) -> u64 {
+            let mut count: u64 = 0;
             for i in sequence {
                 count += 1;
                 if i >= height {
@@ -0,0 +1,66 @@
+use crate::Map;
+
+pub const FROM_TOP: u8 = 1;
+pub const FROM_BOTTOM: u8 = 2;
+pub const FROM_LEFT: u8 = 4;
+pub const FROM_RIGHT: u8 = 8;
+
+// Every direction is a set of lines of tree coordinates ordered from the edge the viewer
+// stands at, so "visible from the left" and "viewing distance to the left" both only
+// look at the trees earlier in the same line.
+fn lines(rows: usize, cols: usize, direction: u8) -> Vec<Vec<(usize, usize)>> {
+    match direction {
+        FROM_LEFT => (0..rows).map(|i| (0..cols).map(|j| (i, j)).collect()).collect(),
+        FROM_RIGHT => (0..rows).map(|i| (0..cols).rev().map(|j| (i, j)).collect()).collect(),
+        FROM_TOP => (0..cols).map(|j| (0..rows).map(|i| (i, j)).collect()).collect(),
+        FROM_BOTTOM => (0..cols).map(|j| (0..rows).rev().map(|i| (i, j)).collect()).collect(),
+        _ => vec![],
+    }
+}
+
+const DIRECTIONS: [u8; 4] = [FROM_TOP, FROM_BOTTOM, FROM_LEFT, FROM_RIGHT];
+
+// Bit mask of directions each tree is visible from, zero means hidden.
+pub fn visibility(map: &Map) -> Vec<Vec<u8>> {
+    let (rows, cols) = map.size();
+    let mut visible = vec![vec![0; cols]; rows];
+
+    for direction in DIRECTIONS {
+        for line in lines(rows, cols, direction) {
+            let mut highest: Option<u32> = None;
+            for (i, j) in line {
+                let height = map.0[i][j];
+                if highest.is_none_or(|x| height > x) {
+                    visible[i][j] |= direction;
+                    highest = Some(height);
+                }
+            }
+        }
+    }
+
+    visible
+}
+
+pub fn scenic_scores(map: &Map) -> Vec<Vec<u64>> {
+    let (rows, cols) = map.size();
+    let mut scores = vec![vec![1; cols]; rows];
+
+    for direction in DIRECTIONS {
+        for line in lines(rows, cols, direction) {
+            // Positions in the line of trees not yet blocked by a taller or equal one, heights decrease to the top.
+            let mut stack: Vec<usize> = vec![];
+            for (position, &(i, j)) in line.iter().enumerate() {
+                let height = map.0[i][j];
+                while stack.last().is_some_and(|&x| map.0[line[x].0][line[x].1] < height) {
+                    stack.pop();
+                }
+
+                let blocking = stack.last().copied().unwrap_or(0);
+                scores[i][j] *= (position - blocking) as u64;
+                stack.push(position);
+            }
+        }
+    }
+
+    scores
+}
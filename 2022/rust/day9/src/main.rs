@@ -1,3 +1,7 @@
+mod render;
+
+use std::env;
+use std::fmt;
 use std::str::FromStr;
 use std::io::{self, BufRead};
 use std::collections::hash_set::HashSet;
@@ -6,38 +10,113 @@ use anyhow::{Result, anyhow, Ok};
 fn main() {
     let stdin = io::stdin();
     let lines = stdin.lock().lines();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = parse_motions(lines).and_then(|motions| match args.first().map(|x| x.as_str()) {
+        Some("part_one") => RopeReport::simulate(&motions, 10, follow_rule(&args)?).map(|x| part_one(&x)),
+        Some("render") => render::run(&motions, &args[1..]),
+        Some("knots") => knots(&motions, &args[1..]),
+        _ => RopeReport::simulate(&motions, 10, follow_rule(&args)?).map(|x| part_two(&x)),
+    });
 
-    part_two(lines).unwrap();
+    if let Err(err) = result {
+        println!("Error: {err:?}");
+    }
 }
 
-fn part_one(lines: io::Lines<io::StdinLock<'_>>) -> Result<()> {
-    let rope = Rope::new(2)?;
-    calculate_visible(lines, rope)
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|x| x == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|x| x.as_str())
 }
 
-fn part_two(lines: io::Lines<io::StdinLock<'_>>) -> Result<()> {
-    let rope = Rope::new(10)?;
-    calculate_visible(lines, rope)
+fn follow_rule(args: &[String]) -> Result<FollowRule> {
+    option_value(args, "--follow").map(|x| x.parse()).unwrap_or(Ok(FollowRule::King))
 }
 
-fn calculate_visible(lines: io::Lines<io::StdinLock<'_>>, mut rope: Rope) -> std::prelude::v1::Result<(), anyhow::Error> {
-    let mut visited_by_tail = HashSet::new();
-    
+fn part_one(report: &RopeReport) {
+    println!("Visited by tail count: {}", report.knots[1].visited.len());
+}
+
+fn part_two(report: &RopeReport) {
+    println!("Visited by tail count: {}", report.knots[9].visited.len());
+}
+
+fn knots(motions: &[Motion], args: &[String]) -> Result<()> {
+    let knots_count = option_value(args, "--knots").map(|x| x.parse()).transpose()?.unwrap_or(10);
+    let report = RopeReport::simulate(motions, knots_count, follow_rule(args)?)?;
+
+    println!("{:>4} {:>8} {:>22} {:>9}", "knot", "visited", "bounding box", "distance");
+    for (i, knot) in report.knots.iter().enumerate() {
+        let bounding_box = format!("({}, {})..({}, {})", knot.min.x, knot.min.y, knot.max.x, knot.max.y);
+        println!("{i:>4} {:>8} {bounding_box:>22} {:>9}", knot.visited.len(), knot.distance);
+    }
+    Ok(())
+}
+
+fn parse_motions(lines: io::Lines<io::StdinLock<'_>>) -> Result<Vec<Motion>> {
+    let mut motions = vec![];
     for line in lines {
-        let line = line?;
-        let (d, n) = &line.split_once(' ').ok_or_else(|| anyhow!("Parsing error"))?;
-        let d: Direction = d.parse()?;
-        let n: u32 = n.parse()?;
-
-        for _ in 0..n {
-            rope.make_step(d);
-            visited_by_tail.insert(rope.get_tail());
+        motions.push(line?.parse()?);
+    }
+    Ok(motions)
+}
+
+// Distance is counted in steps, a diagonal step counts as one like for a king on a chess board.
+struct KnotStats {
+    visited: HashSet<Point>,
+    min: Point,
+    max: Point,
+    distance: u64,
+}
+
+impl KnotStats {
+    fn new(start: Point) -> Self {
+        KnotStats { visited: HashSet::from([start]), min: start, max: start, distance: 0 }
+    }
+
+    fn track(&mut self, from: Point, to: Point) {
+        if from == to {
+            return;
         }
+        self.visited.insert(to);
+        self.min = Point { x: self.min.x.min(to.x), y: self.min.y.min(to.y) };
+        self.max = Point { x: self.max.x.max(to.x), y: self.max.y.max(to.y) };
+        let (dx, dy) = (to.x as i64 - from.x as i64, to.y as i64 - from.y as i64);
+        self.distance += dx.unsigned_abs().max(dy.unsigned_abs());
     }
+}
 
-    println!("Visited by tail count: {}", visited_by_tail.len());
+struct RopeReport {
+    knots: Vec<KnotStats>,
+}
 
-    Ok(())
+// Visited cells of all knots together, long jumps stop with an error instead of filling the memory.
+const VISITED_LIMIT: usize = 1 << 24;
+
+impl RopeReport {
+    fn simulate(motions: &[Motion], knots_count: usize, follow_rule: FollowRule) -> Result<Self> {
+        let mut rope = Rope::new(knots_count, follow_rule)?;
+        let mut knots = rope.knots().map(KnotStats::new).collect::<Vec<_>>();
+        let mut visited = knots.len();
+
+        for motion in motions {
+            for _ in 0..motion.steps {
+                rope.make_step(motion.direction, |i, from, to| {
+                    visited -= knots[i].visited.len();
+                    knots[i].track(from, to);
+                    visited += knots[i].visited.len();
+                    if visited > VISITED_LIMIT {
+                        return Err(anyhow!("Knots visited more than {VISITED_LIMIT} cells, stopped at motion {motion}"));
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+
+        Ok(RopeReport { knots })
+    }
 }
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq)]
@@ -52,6 +131,27 @@ enum Direction {
     Down,
     Left,
     Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+    Jump(i32, i32),
+}
+
+impl Direction {
+    fn delta(&self) -> (i32, i32) {
+        match *self {
+            Direction::Up => (0, 1),
+            Direction::Down => (0, -1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::UpLeft => (-1, 1),
+            Direction::UpRight => (1, 1),
+            Direction::DownLeft => (-1, -1),
+            Direction::DownRight => (1, -1),
+            Direction::Jump(dx, dy) => (dx, dy),
+        }
+    }
 }
 
 impl FromStr for Direction {
@@ -63,25 +163,119 @@ impl FromStr for Direction {
             "D" => std::result::Result::Ok(Direction::Down),
             "L" => std::result::Result::Ok(Direction::Left),
             "R" => std::result::Result::Ok(Direction::Right),
-            _ => Err(anyhow!("Failed to parse Direction from {s}")),
+            "UL" | "LU" => std::result::Result::Ok(Direction::UpLeft),
+            "UR" | "RU" => std::result::Result::Ok(Direction::UpRight),
+            "DL" | "LD" => std::result::Result::Ok(Direction::DownLeft),
+            "DR" | "RD" => std::result::Result::Ok(Direction::DownRight),
+            _ => match s.split_once(',') {
+                Some((dx, dy)) => Ok(Direction::Jump(dx.trim().parse()?, dy.trim().parse()?)),
+                None => Err(anyhow!("Failed to parse Direction from {s}")),
+            },
         }
     }
    
 }
 
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Up => write!(f, "U"),
+            Direction::Down => write!(f, "D"),
+            Direction::Left => write!(f, "L"),
+            Direction::Right => write!(f, "R"),
+            Direction::UpLeft => write!(f, "UL"),
+            Direction::UpRight => write!(f, "UR"),
+            Direction::DownLeft => write!(f, "DL"),
+            Direction::DownRight => write!(f, "DR"),
+            Direction::Jump(dx, dy) => write!(f, "{dx},{dy}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum FollowRule {
+    King,
+    Orthogonal,
+    Elastic(u32),
+}
+
+impl FollowRule {
+    // Differences are taken in i64, knots at opposite ends of the i32 range are still apart.
+    fn is_close_enough(&self, knot: Point, heading_knot: Point) -> bool {
+        let dx = (heading_knot.x as i64 - knot.x as i64).unsigned_abs();
+        let dy = (heading_knot.y as i64 - knot.y as i64).unsigned_abs();
+        match self {
+            FollowRule::King => dx <= 1 && dy <= 1,
+            FollowRule::Orthogonal => dx + dy <= 1,
+            FollowRule::Elastic(k) => dx <= *k as u64 && dy <= *k as u64,
+        }
+    }
+
+    fn step_towards(&self, knot: &mut Point, heading_knot: Point) {
+        let dx = heading_knot.x as i64 - knot.x as i64;
+        let dy = heading_knot.y as i64 - knot.y as i64;
+        match self {
+            FollowRule::King | FollowRule::Elastic(_) => {
+                knot.x += dx.signum() as i32;
+                knot.y += dy.signum() as i32;
+            },
+            FollowRule::Orthogonal if dx.abs() >= dy.abs() => knot.x += dx.signum() as i32,
+            FollowRule::Orthogonal => knot.y += dy.signum() as i32,
+        }
+    }
+}
+
+impl FromStr for FollowRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "king" => Ok(FollowRule::King),
+            "orthogonal" => Ok(FollowRule::Orthogonal),
+            _ => match s.strip_prefix("elastic:") {
+                Some(k) => Ok(FollowRule::Elastic(k.parse()?)),
+                None => Err(anyhow!("Failed to parse FollowRule from {s}, expected king, orthogonal or elastic:<k>")),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Motion {
+    direction: Direction,
+    steps: u32,
+}
+
+impl FromStr for Motion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (d, n) = s.split_once(' ').ok_or_else(|| anyhow!("Parsing error"))?;
+        Ok(Motion { direction: d.parse()?, steps: n.parse()? })
+    }
+}
+
+impl fmt::Display for Motion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.direction, self.steps)
+    }
+}
+
 struct Rope {
     head: Point,
     body: Vec<Point>,
+    follow_rule: FollowRule,
 }
 
 impl Rope {
-    fn new(length: usize) -> Result<Self> {
+    fn new(length: usize, follow_rule: FollowRule) -> Result<Self> {
         if length < 2 {
             return Err(anyhow!("Length of rope must be at least 2 knots"));
         }
         Ok(Rope {
             head: Point { x: 0, y: 0 },
-            body: vec![Point { x: 0, y: 0 }; length - 1]
+            body: vec![Point { x: 0, y: 0 }; length - 1],
+            follow_rule,
         })
     }
 
@@ -89,35 +283,42 @@ impl Rope {
         *self.body.last().unwrap()
     }
 
-    fn make_step(&mut self, d: Direction) {
-        match d {
-            Direction::Up => self.head.y += 1,
-            Direction::Down =>  self.head.y -= 1,
-            Direction::Left => self.head.x -= 1,
-            Direction::Right => self.head.x += 1,
-        }
+    fn knots(&self) -> impl Iterator<Item = Point> + '_ {
+        std::iter::once(self.head).chain(self.body.iter().copied())
+    }
+
+    // Every move of a knot is passed in order to on_move as (knot index, from, to) with the head at 0.
+    fn make_step(&mut self, d: Direction, mut on_move: impl FnMut(usize, Point, Point) -> Result<()>) -> Result<()> {
+        let (dx, dy) = d.delta();
+        let from = self.head;
+        self.head = match (from.x.checked_add(dx), from.y.checked_add(dy)) {
+            (Some(x), Some(y)) => Point { x, y },
+            _ => return Err(anyhow!("Head moves out of range from ({}, {}) by {d}", from.x, from.y)),
+        };
+        on_move(0, from, self.head)?;
 
         let mut heading_knot = self.head;
-        for knot in &mut self.body {
-            heading_knot = match try_move_body_knot(knot, heading_knot) {
+        for (i, knot) in self.body.iter_mut().enumerate() {
+            heading_knot = match try_move_body_knot(knot, heading_knot, self.follow_rule, |from, to| on_move(i + 1, from, to))? {
                 Some(h) => h,
-                None => return,
+                None => break,
             }
         }
+        return Ok(());
 
-        fn try_move_body_knot(knot_to_move: &mut Point, heading_knot: Point) -> Option<Point> {
-            if (heading_knot.x - knot_to_move.x).abs() <= 1 && (heading_knot.y - knot_to_move.y).abs() <= 1 {
-                return None;
+        // After a jump of the head a knot may need several steps to catch up, each one is reported.
+        fn try_move_body_knot(knot_to_move: &mut Point, heading_knot: Point, follow_rule: FollowRule, mut on_step: impl FnMut(Point, Point) -> Result<()>) -> Result<Option<Point>> {
+            if follow_rule.is_close_enough(*knot_to_move, heading_knot) {
+                return Ok(None);
             }
 
-            if heading_knot.x != knot_to_move.x {
-                knot_to_move.x += (heading_knot.x - knot_to_move.x).signum();
-            }
-            if heading_knot.y != knot_to_move.y {
-                knot_to_move.y += (heading_knot.y - knot_to_move.y).signum();
+            while !follow_rule.is_close_enough(*knot_to_move, heading_knot) {
+                let from = *knot_to_move;
+                follow_rule.step_towards(knot_to_move, heading_knot);
+                on_step(from, *knot_to_move)?;
             }
 
-            Some(*knot_to_move)
+            Ok(Some(*knot_to_move))
         }
     }
 }
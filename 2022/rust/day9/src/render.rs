@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+use anyhow::Result;
+
+use crate::{Motion, Point, Rope, RopeReport, follow_rule, option_value};
+
+struct Bounds {
+    min: Point,
+    max: Point,
+}
+
+impl Bounds {
+    fn of(report: &RopeReport) -> Self {
+        let mut bounds = Bounds { min: Point { x: 0, y: 0 }, max: Point { x: 0, y: 0 } };
+        for knot in report.knots.iter() {
+            bounds.min = Point { x: bounds.min.x.min(knot.min.x), y: bounds.min.y.min(knot.min.y) };
+            bounds.max = Point { x: bounds.max.x.max(knot.max.x), y: bounds.max.y.max(knot.max.y) };
+        }
+        bounds
+    }
+
+    // Rows go from the top (largest y) to the bottom, like in the puzzle explanations.
+    fn draw(&self, cell: impl Fn(Point) -> char) -> String {
+        let mut drawing = String::new();
+        for y in (self.min.y..=self.max.y).rev() {
+            for x in self.min.x..=self.max.x {
+                drawing.push(cell(Point { x, y }));
+            }
+            drawing.push('\n');
+        }
+        drawing
+    }
+}
+
+fn knot_label(index: usize, knots_count: usize) -> char {
+    match index {
+        0 => 'H',
+        i if i == knots_count - 1 && knots_count == 2 => 'T',
+        i => char::from_digit(i as u32, 36).map(|x| x.to_ascii_uppercase()).unwrap_or('*'),
+    }
+}
+
+fn draw_rope(rope: &Rope, bounds: &Bounds) -> String {
+    let knots = rope.knots().collect::<Vec<_>>();
+    bounds.draw(|point| {
+        match knots.iter().position(|&knot| knot == point) {
+            Some(i) => knot_label(i, knots.len()),
+            None if point == (Point { x: 0, y: 0 }) => 's',
+            None => '.',
+        }
+    })
+}
+
+fn draw_visited(visited: &HashSet<Point>, bounds: &Bounds) -> String {
+    bounds.draw(|point| {
+        if point == (Point { x: 0, y: 0 }) {
+            's'
+        } else if visited.contains(&point) {
+            '#'
+        } else {
+            '.'
+        }
+    })
+}
+
+pub fn run(motions: &[Motion], args: &[String]) -> Result<()> {
+    let knots_count = option_value(args, "--knots").map(|x| x.parse()).transpose()?.unwrap_or(10);
+    let animate = args.iter().any(|x| x == "--animate");
+    let delay = option_value(args, "--delay").map(|x| x.parse()).transpose()?.unwrap_or(50);
+
+    // The whole simulation is run once up front so every frame uses the same bounds.
+    let follow_rule = follow_rule(args)?;
+    let bounds = Bounds::of(&RopeReport::simulate(motions, knots_count, follow_rule)?);
+
+    let mut rope = Rope::new(knots_count, follow_rule)?;
+    let mut visited_by_tail = HashSet::from([rope.get_tail()]);
+    let tail = knots_count - 1;
+    if !animate {
+        println!("== Initial State ==\n\n{}", draw_rope(&rope, &bounds));
+    }
+    for motion in motions {
+        for step in 0..motion.steps {
+            rope.make_step(motion.direction, |i, _, to| {
+                if i == tail {
+                    visited_by_tail.insert(to);
+                }
+                Ok(())
+            })?;
+            if animate {
+                print!("\x1b[2J\x1b[H== {motion} ({}/{}) ==\n\n{}", step + 1, motion.steps, draw_rope(&rope, &bounds));
+                thread::sleep(Duration::from_millis(delay));
+            }
+        }
+        if !animate {
+            println!("== {motion} ==\n\n{}", draw_rope(&rope, &bounds));
+        }
+    }
+
+    println!("== Visited by tail ==\n\n{}", draw_visited(&visited_by_tail, &bounds));
+    println!("Visited by tail count: {}", visited_by_tail.len());
+    Ok(())
+}